@@ -0,0 +1,347 @@
+use std::io::Read;
+use std::sync::{Arc, atomic::Ordering};
+use std::time::Duration;
+use parking_lot::RwLock;
+use rand::Rng;
+use rand::distr::Alphanumeric;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracing::{debug, info, warn};
+
+use crate::clicker::ClickerControls;
+use crate::cli::WindowEntry;
+use crate::timing::{CPS_RANGE, INTERVAL_RANGE};
+use crate::window_manager::{WindowInfo, WindowManager};
+use crate::worker::Worker;
+
+/// Largest request body accepted; the JSON bodies are a few dozen bytes.
+const MAX_BODY: usize = 1024;
+
+/// How the control API is exposed; off unless the user turns it on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiSettings {
+    pub enabled: bool,
+    /// Port on 127.0.0.1.
+    pub port: u16,
+    /// Listen on this Unix domain socket instead of the port.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unix_socket: Option<String>,
+    /// Every request must carry `Authorization: Bearer <token>`; generated
+    /// when the API is first turned on.
+    pub token: String,
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 8765,
+            unix_socket: None,
+            token: String::new(),
+        }
+    }
+}
+
+pub fn new_token() -> String {
+    rand::rng().sample_iter(Alphanumeric).take(32).map(char::from).collect()
+}
+
+/// What the API can see and drive: the jobs by name, in the app's order.
+/// The app refreshes it whenever jobs are added, removed or renamed.
+#[derive(Clone, Default)]
+pub struct ApiState {
+    pub jobs: Vec<(String, ClickerControls)>,
+    /// Job used when a request does not name one.
+    pub active: usize,
+}
+
+pub type SharedApiState = Arc<RwLock<ApiState>>;
+
+/// The embedded server thread; stopping or dropping it closes the listener.
+pub struct ApiServer {
+    /// Where it listens, for the UI.
+    pub address: String,
+    _worker: Worker,
+}
+
+impl ApiServer {
+    pub fn start(settings: &ApiSettings, state: SharedApiState) -> Result<Self, String> {
+        if settings.token.is_empty() {
+            return Err("no token set".to_string());
+        }
+        let (server, address) = Self::bind(settings)?;
+        let token = settings.token.clone();
+        info!(%address, "control API listening");
+        let worker = Worker::spawn("api", move |shutdown| {
+            while !shutdown.load(Ordering::SeqCst) {
+                match server.recv_timeout(Duration::from_millis(100)) {
+                    Ok(Some(request)) => respond(request, &token, &state),
+                    Ok(None) => {}
+                    Err(error) => {
+                        warn!(%error, "control API stopped");
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(Self { address, _worker: worker })
+    }
+
+    fn bind(settings: &ApiSettings) -> Result<(Server, String), String> {
+        #[cfg(unix)]
+        if let Some(path) = settings.unix_socket.as_deref().filter(|path| !path.is_empty()) {
+            // A socket file left by a previous run would make the bind fail.
+            // Anything else at that path is the user's and stays.
+            use std::os::unix::fs::FileTypeExt;
+            if std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket()) {
+                std::fs::remove_file(path).ok();
+            }
+            let server = Server::http_unix(std::path::Path::new(path)).map_err(|e| e.to_string())?;
+            return Ok((server, format!("unix:{}", path)));
+        }
+        let address = format!("127.0.0.1:{}", settings.port);
+        let server = Server::http(&address).map_err(|e| e.to_string())?;
+        Ok((server, format!("http://{}", address)))
+    }
+}
+
+fn respond(mut request: Request, token: &str, state: &SharedApiState) {
+    let authorized = header(&request, "Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes()));
+    let streamed = header(&request, "Transfer-Encoding").is_some() || header(&request, "Expect").is_some();
+
+    // Nothing is read from the socket on this thread: tiny_http has no read
+    // timeout to set, but it reads bodies up to `MAX_BODY` itself before
+    // handing the request over. Anything that would stream is refused.
+    let (status, value) = if !authorized {
+        (401, json!({ "error": "missing or wrong token" }))
+    } else if streamed || request.body_length().is_some_and(|length| length > MAX_BODY) {
+        (413, json!({ "error": format!("body must be sent with Content-Length, at most {} bytes", MAX_BODY) }))
+    } else {
+        let mut body = String::new();
+        match request.as_reader().take(MAX_BODY as u64).read_to_string(&mut body) {
+            Ok(_) => handle(request.method(), request.url(), &body, state),
+            Err(e) => (400, json!({ "error": e.to_string() })),
+        }
+    };
+    debug!(method = %request.method(), url = request.url(), status, "control API request");
+
+    let response = Response::from_string(value.to_string())
+        .with_status_code(status)
+        .with_header(Header::from_bytes("Content-Type", "application/json").expect("static header"));
+    request.respond(response).ok();
+}
+
+fn header<'a>(request: &'a Request, name: &'static str) -> Option<&'a str> {
+    request.headers().iter().find(|header| header.field.equiv(name)).map(|header| header.value.as_str())
+}
+
+// Compares the whole token whatever the input, so response times do not
+// reveal how much of a guess was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[derive(Deserialize)]
+struct IntervalBody {
+    interval_ms: Option<u64>,
+    cps: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct TargetBody {
+    id: Option<isize>,
+    title: Option<String>,
+    /// Narrows a title match to one executable.
+    process: Option<String>,
+}
+
+/// Routes one authorized request. `?job=` picks a job by name or index;
+/// without it the job selected in the window is used.
+fn handle(method: &Method, url: &str, body: &str, state: &SharedApiState) -> (u16, Value) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    if (method, path) == (&Method::Get, "/windows") {
+        return (200, windows_json(&WindowManager::get_windows_list()));
+    }
+
+    let state = state.read();
+    let job = query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("job="))
+        .map(|wanted| {
+            let wanted = decode(wanted);
+            state
+                .jobs
+                .iter()
+                .position(|(name, _)| *name == wanted)
+                .or_else(|| wanted.parse().ok().filter(|index| *index < state.jobs.len()))
+        })
+        .unwrap_or(Some(state.active));
+    let Some((name, controls)) = job.and_then(|index| state.jobs.get(index)) else {
+        return (404, json!({ "error": "no such job" }));
+    };
+
+    match (method, path) {
+        (Method::Get, "/status") => (200, status_json(name, controls)),
+        (Method::Post, "/start") => {
+            if controls.selected_hwnd.read().is_none() {
+                return (409, json!({ "error": "job has no target window" }));
+            }
+            controls.running.store(true, Ordering::SeqCst);
+            info!(job = %name, "started through the control API");
+            (200, status_json(name, controls))
+        }
+        (Method::Post, "/stop") => {
+            controls.running.store(false, Ordering::SeqCst);
+            info!(job = %name, "stopped through the control API");
+            (200, status_json(name, controls))
+        }
+        (Method::Put, "/interval") => match serde_json::from_str::<IntervalBody>(body) {
+            // Same ranges as the sliders in the window.
+            Ok(IntervalBody { cps: Some(cps), .. }) if CPS_RANGE.contains(&cps) => {
+                *controls.cps.write() = Some(cps);
+                (200, status_json(name, controls))
+            }
+            Ok(IntervalBody { cps: Some(_), .. }) => {
+                (400, json!({ "error": format!("cps must be {} to {}", CPS_RANGE.start(), CPS_RANGE.end()) }))
+            }
+            Ok(IntervalBody { interval_ms: Some(ms), cps: None }) if INTERVAL_RANGE.contains(&ms) => {
+                *controls.interval_ms.write() = ms;
+                *controls.cps.write() = None;
+                (200, status_json(name, controls))
+            }
+            Ok(IntervalBody { interval_ms: Some(_), cps: None }) => (
+                400,
+                json!({ "error": format!("interval_ms must be {} to {}", INTERVAL_RANGE.start(), INTERVAL_RANGE.end()) }),
+            ),
+            Ok(_) => (400, json!({ "error": "expected {\"interval_ms\": n} or {\"cps\": n}" })),
+            Err(e) => (400, json!({ "error": e.to_string() })),
+        },
+        (Method::Put, "/target") => match serde_json::from_str::<TargetBody>(body) {
+            Ok(target) => {
+                let windows = WindowManager::get_windows_list();
+                let found = windows.iter().find(|window| {
+                    target.id == Some(window.hwnd)
+                        || (target.title.as_deref() == Some(window.title.as_str())
+                            && target.process.as_ref().is_none_or(|process| window.process.as_ref() == Some(process)))
+                });
+                match found {
+                    Some(window) => {
+                        *controls.selected_hwnd.write() = Some(window.hwnd);
+                        (200, status_json(name, controls))
+                    }
+                    None => (404, json!({ "error": "window not found" })),
+                }
+            }
+            Err(e) => (400, json!({ "error": e.to_string() })),
+        },
+        (_, "/status" | "/start" | "/stop" | "/interval" | "/target" | "/windows") => {
+            (405, json!({ "error": "method not allowed" }))
+        }
+        _ => (404, json!({ "error": "unknown endpoint" })),
+    }
+}
+
+fn windows_json(windows: &[WindowInfo]) -> Value {
+    windows.iter().map(|window| json!(WindowEntry::from(window))).collect()
+}
+
+fn status_json(name: &str, controls: &ClickerControls) -> Value {
+    let hwnd = *controls.selected_hwnd.read();
+    let stats = controls.stats.read();
+    json!({
+        "job": name,
+        "running": controls.running.load(Ordering::SeqCst),
+        "interval_ms": *controls.interval_ms.read(),
+        "cps": *controls.cps.read(),
+        "window": hwnd.map(|id| json!({ "id": id, "title": WindowManager::window_title(id) })),
+        "session": {
+            "clicks": stats.clicks,
+            "failed": stats.failed,
+            "measured_cps": stats.measured_cps,
+        },
+    })
+}
+
+// Enough percent-decoding for job names in a query string.
+fn decode(text: &str) -> String {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        rest = tail;
+        match byte {
+            b'%' => {
+                let hex = tail.get(..2).and_then(|hex| std::str::from_utf8(hex).ok());
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(value) => {
+                        bytes.push(value);
+                        rest = &tail[2..];
+                    }
+                    None => bytes.push(byte),
+                }
+            }
+            b'+' => bytes.push(b' '),
+            _ => bytes.push(byte),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> SharedApiState {
+        let first = ClickerControls::default();
+        *first.selected_hwnd.write() = Some(42);
+        Arc::new(RwLock::new(ApiState {
+            jobs: vec![("Farm".to_string(), first), ("Idle job".to_string(), ClickerControls::default())],
+            active: 0,
+        }))
+    }
+
+    #[test]
+    fn start_stop_and_interval_act_on_shared_controls() {
+        let state = state();
+        let controls = state.read().jobs[0].1.clone();
+
+        assert_eq!(handle(&Method::Post, "/start", "", &state).0, 200);
+        assert!(controls.running.load(Ordering::SeqCst));
+        assert_eq!(handle(&Method::Put, "/interval", r#"{"interval_ms": 250}"#, &state).0, 200);
+        assert_eq!(*controls.interval_ms.read(), 250);
+        assert_eq!(handle(&Method::Put, "/interval", r#"{"cps": 20}"#, &state).0, 200);
+        assert_eq!(*controls.cps.read(), Some(20.0));
+        assert_eq!(handle(&Method::Put, "/interval", r#"{"interval_ms": 0}"#, &state).0, 400);
+        assert_eq!(handle(&Method::Put, "/interval", r#"{"interval_ms": 5000}"#, &state).0, 400);
+        assert_eq!(handle(&Method::Put, "/interval", r#"{"cps": 5000}"#, &state).0, 400);
+        assert_eq!(handle(&Method::Put, "/interval", r#"{"cps": 0.1}"#, &state).0, 400);
+        assert_eq!(*controls.cps.read(), Some(20.0));
+        assert_eq!(handle(&Method::Post, "/stop", "", &state).0, 200);
+        assert!(!controls.running.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn picks_job_from_query() {
+        let state = state();
+        let (status, body) = handle(&Method::Get, "/status?job=Idle%20job", "", &state);
+        assert_eq!(status, 200);
+        assert_eq!(body["job"], "Idle job");
+        // The second job has no window to click into.
+        assert_eq!(handle(&Method::Post, "/start?job=1", "", &state).0, 409);
+        assert_eq!(handle(&Method::Get, "/status?job=Missing", "", &state).0, 404);
+    }
+
+    #[test]
+    fn unknown_routes_and_methods() {
+        let state = state();
+        assert_eq!(handle(&Method::Get, "/start", "", &state).0, 405);
+        assert_eq!(handle(&Method::Get, "/nope", "", &state).0, 404);
+        assert_eq!(decode("a+b%2Fc"), "a b/c");
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+    }
+}
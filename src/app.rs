@@ -1,100 +1,397 @@
-use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
-use parking_lot::RwLock;
+use std::sync::{atomic::Ordering, mpsc::Receiver};
 use eframe::egui;
 use egui::{Color32, RichText, Stroke};
+use tracing::{info, warn};
 
 use crate::{
-    window_manager::WindowManager,
-    hotkey_manager::HotkeyManager,
-    clicker::Clicker,
-    ui::{top_panel::TopPanel, windows_list::WindowsList, settings_panel::SettingsPanel}
+    api::{ApiServer, ApiSettings, ApiState, SharedApiState},
+    backend::MouseButton,
+    window_manager::{WindowInfo, WindowManager},
+    hotkey_manager::{HotkeyManager, KeyEdge},
+    clicker::ClickPoint,
+    job::Job,
+    logging::Logging,
+    macros::{Macro, recorder::MacroRecorder},
+    settings::Settings,
+    timing::{self, IntervalDistribution},
+    schedule::Due,
+    session::StopRules,
+    ui::{
+        top_panel::TopPanel,
+        jobs_panel::{JobsPanel, JobAction},
+        settings_panel::SettingsPanel,
+        macro_panel::{MacroPanel, MacroAction},
+        script_panel::ScriptPanel,
+        schedule_panel::{SchedulePanel, ScheduleAction},
+        log_panel::LogPanel,
+        api_panel::ApiPanel,
+    }
 };
 
 pub struct MyApp {
-    windows: Vec<(String, isize)>,
-    selected_hwnd: Arc<RwLock<Option<isize>>>,
-    hotkey_vk: Arc<RwLock<u32>>,
-    running: Arc<AtomicBool>,
-    interval_ms: Arc<RwLock<u64>>,
-    hold_shift: Arc<AtomicBool>,
-    hold_ctrl: Arc<AtomicBool>,
+    windows: Vec<WindowInfo>,
+    /// One per profile, same order; `settings.active_profile` is the one
+    /// being edited.
+    jobs: Vec<Job>,
+    recorder: Option<MacroRecorder>,
+    recorded_macro: Option<Macro>,
     animation_progress: f32,
     last_update: std::time::Instant,
-    listening_for_key: bool,
+    picking_point: bool,
+    last_schedule_check: std::time::Instant,
     last_key_press: Option<u32>,
+    key_events: Receiver<KeyEdge>,
+    // `settings` is the working copy (the active profile is refreshed from
+    // the live values every frame); `saved_settings` is what is on disk.
+    settings: Settings,
+    saved_settings: Settings,
+    logging: Logging,
+    api_state: SharedApiState,
+    /// The control API as last (re)started, and the settings it used.
+    api_server: Option<Result<ApiServer, String>>,
+    api_config: Option<ApiSettings>,
 
     // UI components
     top_panel: TopPanel,
-    windows_list: WindowsList,
+    jobs_panel: JobsPanel,
     settings_panel: SettingsPanel,
+    macro_panel: MacroPanel,
+    script_panel: ScriptPanel,
+    schedule_panel: SchedulePanel,
+    log_panel: LogPanel,
+    api_panel: ApiPanel,
 }
 
 impl MyApp {
-    pub fn new() -> Self {
+    pub fn new(settings: Settings, logging: Logging) -> Self {
         let windows = WindowManager::get_windows_list();
-        let selected_hwnd = Arc::new(RwLock::new(None));
-        let hotkey_vk = Arc::new(RwLock::new(HotkeyManager::vk_for_label("F6")));
-        let running = Arc::new(AtomicBool::new(false));
-        let interval_ms = Arc::new(RwLock::new(500u64));
-        let hold_shift = Arc::new(AtomicBool::new(false));
-        let hold_ctrl = Arc::new(AtomicBool::new(false));
-        let animation_progress = 0.0;
-        let last_update = std::time::Instant::now();
-        let listening_for_key = false;
-        let last_key_press = None;
-
-        HotkeyManager::start_hotkey_listener(hotkey_vk.clone(), running.clone());
-        Clicker::start_clicker(
-            running.clone(),
-            selected_hwnd.clone(),
-            interval_ms.clone(),
-            hold_shift.clone(),
-            hold_ctrl.clone(),
-        );
+        let jobs: Vec<Job> = settings.profiles.iter().map(|profile| Job::spawn(profile, &windows)).collect();
+        let window_filter = settings.window_filter.clone();
+        let recent_windows = settings.recent_windows.clone();
+        let api_settings = settings.api.clone();
 
         Self {
             windows,
-            selected_hwnd,
-            hotkey_vk,
-            running,
-            interval_ms,
-            hold_shift,
-            hold_ctrl,
-            animation_progress,
-            last_update,
-            listening_for_key,
-            last_key_press,
+            jobs,
+            recorder: None,
+            recorded_macro: None,
+            animation_progress: 0.0,
+            last_update: std::time::Instant::now(),
+            picking_point: false,
+            last_schedule_check: std::time::Instant::now(),
+            last_key_press: None,
+            key_events: HotkeyManager::subscribe(),
+            saved_settings: settings.clone(),
+            settings,
+            logging,
+            api_state: SharedApiState::default(),
+            api_server: None,
+            api_config: None,
 
             top_panel: TopPanel,
-            windows_list: WindowsList::new(),
-            settings_panel: SettingsPanel,
+            jobs_panel: JobsPanel::new(window_filter, recent_windows),
+            settings_panel: SettingsPanel::new(),
+            macro_panel: MacroPanel::new(),
+            script_panel: ScriptPanel,
+            schedule_panel: SchedulePanel::new(),
+            log_panel: LogPanel,
+            api_panel: ApiPanel::new(api_settings),
+        }
+    }
+
+    /// The job the settings panels edit.
+    fn job(&self) -> &Job {
+        &self.jobs[self.settings.active_profile]
+    }
+
+    fn selected_hwnd(&self) -> Option<isize> {
+        *self.job().controls.selected_hwnd.read()
+    }
+
+    fn refresh_windows(&mut self) {
+        self.windows = WindowManager::get_windows_list();
+        for (job, profile) in self.jobs.iter().zip(&self.settings.profiles) {
+            job.reconnect(profile, &self.windows);
+        }
+    }
+
+    fn sync_profiles(&mut self) {
+        for (job, profile) in self.jobs.iter().zip(&mut self.settings.profiles) {
+            job.store(profile, &self.windows);
+        }
+    }
+
+    fn apply_job_action(&mut self, action: JobAction) {
+        match action {
+            JobAction::Select(index) => {
+                // Picking and recording belong to the job they started for.
+                self.picking_point = false;
+                if self.recorder.is_some() {
+                    self.toggle_recording();
+                }
+                self.settings.active_profile = index;
+            }
+            JobAction::Add(name) => {
+                self.sync_profiles();
+                let mut profile = self.settings.profiles[self.settings.active_profile].clone();
+                profile.name = name;
+                // A shared key would toggle both jobs at once.
+                profile.hotkey_vk = 0;
+                self.jobs.push(Job::spawn(&profile, &self.windows));
+                self.settings.profiles.push(profile);
+                self.settings.active_profile = self.settings.profiles.len() - 1;
+            }
+            JobAction::Remove(index) => {
+                if self.settings.profiles.len() < 2 {
+                    return;
+                }
+                self.jobs.remove(index).shutdown();
+                self.settings.profiles.remove(index);
+                let active = self.settings.active_profile;
+                if index < active || active == self.settings.profiles.len() {
+                    self.settings.active_profile = active - 1;
+                }
+            }
+            JobAction::Rename(index, name) => {
+                let old = std::mem::replace(&mut self.settings.profiles[index].name, name.clone());
+                for entry in self.settings.schedules.iter_mut().filter(|entry| entry.profile == old) {
+                    entry.profile = name.clone();
+                }
+            }
+            JobAction::ClearHotkey => self.settings.profile_hotkey_vk = 0,
+        }
+    }
+
+    fn toggle_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            let recorded = recorder.finish();
+            if recorded.steps.is_empty() {
+                return;
+            }
+            if self.job().controls.playback.read().is_some() {
+                *self.job().controls.playback.write() = Some(recorded.clone());
+            }
+            self.recorded_macro = Some(recorded);
+        } else if let Some(hwnd) = self.selected_hwnd() {
+            // The clicker would otherwise end up in the recording.
+            self.job().controls.running.store(false, Ordering::SeqCst);
+            self.recorder = Some(MacroRecorder::start(hwnd, self.settings.macro_hotkey_vk));
+        }
+    }
+
+    fn apply_macro_action(&mut self, action: MacroAction) {
+        match action {
+            MacroAction::ToggleRecording => self.toggle_recording(),
+            MacroAction::SetPlayback(play) => {
+                *self.job().controls.playback.write() = if play { self.recorded_macro.clone() } else { None };
+            }
+            MacroAction::Clear => {
+                self.recorded_macro = None;
+                *self.job().controls.playback.write() = None;
+            }
+            MacroAction::ClearHotkey => self.settings.macro_hotkey_vk = 0,
+            MacroAction::UseTimings => {
+                if let Some(recorded) = &self.recorded_macro {
+                    let buckets = timing::histogram(&recorded.press_intervals());
+                    self.job().controls.timing.write().distribution = IntervalDistribution::Histogram { buckets };
+                }
+            }
+            MacroAction::Save(name) => {
+                let result = match (&self.recorded_macro, Macro::saved_path(&name)) {
+                    (Some(recorded), Some(path)) => recorded.save_to(&path).map(|_| path),
+                    _ => Err("no config directory".into()),
+                };
+                self.macro_panel.message = Some(match result {
+                    Ok(path) => format!("Сохранено: {}", path.display()),
+                    Err(e) => format!("⚠ Не удалось сохранить: {}", e),
+                });
+                self.macro_panel.refresh_saved();
+            }
+            MacroAction::Load(name) => {
+                let result = Macro::saved_path(&name)
+                    .ok_or_else(|| "no config directory".into())
+                    .and_then(|path| Macro::load_from(&path));
+                match result {
+                    Ok(loaded) => {
+                        if self.job().controls.playback.read().is_some() {
+                            *self.job().controls.playback.write() = Some(loaded.clone());
+                        }
+                        self.recorded_macro = Some(loaded);
+                        self.macro_panel.message = None;
+                    }
+                    Err(e) => self.macro_panel.message = Some(format!("⚠ {}: {}", name, e)),
+                }
+            }
+        }
+    }
+
+    fn apply_schedule_action(&mut self, action: ScheduleAction) {
+        match action {
+            ScheduleAction::Add(entry) => self.settings.schedules.push(entry),
+            ScheduleAction::Remove(index) => {
+                self.settings.schedules.remove(index);
+            }
+            ScheduleAction::SetEnabled(index, enabled) => self.settings.schedules[index].enabled = enabled,
+        }
+    }
+
+    // Polled from `update` once a second. Wall-clock time is used so a wake
+    // from sleep or a restart shows up as a late occurrence.
+    fn run_schedules(&mut self) {
+        if self.last_schedule_check.elapsed() < std::time::Duration::from_secs(1) {
+            return;
+        }
+        self.last_schedule_check = std::time::Instant::now();
+
+        let now = chrono::Local::now().naive_local();
+        for index in 0..self.settings.schedules.len() {
+            match self.settings.schedules[index].poll(now) {
+                Some(Due::OnTime) | Some(Due::Late) => self.start_scheduled(index),
+                Some(Due::Missed) => {
+                    let entry = &self.settings.schedules[index];
+                    info!(profile = %entry.profile, trigger = %entry.trigger.describe(), "scheduled run missed");
+                    self.schedule_panel.message = Some(format!(
+                        "Пропущен запуск {} ({})",
+                        entry.trigger.describe(),
+                        entry.profile
+                    ));
+                }
+                None => {}
+            }
+        }
+    }
+
+    // Starts the entry's job; the others keep running. A run that is already
+    // going keeps its session, with the entry's duration applied on top.
+    fn start_scheduled(&mut self, index: usize) {
+        let entry = self.settings.schedules[index].clone();
+        let Some(profile) = self.settings.profiles.iter().position(|profile| profile.name == entry.profile) else {
+            warn!(profile = %entry.profile, "scheduled job not found");
+            self.schedule_panel.message = Some(format!("⚠ Задание «{}» не найдено", entry.profile));
+            return;
+        };
+        self.refresh_windows();
+        let controls = &self.jobs[profile].controls;
+        if let Some(target) = &entry.target {
+            *controls.selected_hwnd.write() = target.find(&self.windows);
+        }
+        if controls.selected_hwnd.read().is_none() {
+            warn!(profile = %entry.profile, "scheduled start has no window");
+            self.schedule_panel.message = Some(format!("⚠ {}: окно не найдено", entry.trigger.describe()));
+            return;
+        }
+        *controls.run_limits.write() = StopRules {
+            max_duration_secs: entry.run_for_secs,
+            ..StopRules::default()
+        };
+        info!(profile = %entry.profile, run_for_secs = ?entry.run_for_secs, "scheduled start");
+        controls.running.store(true, Ordering::SeqCst);
+        self.schedule_panel.message = Some(format!(
+            "Запущено по расписанию: {} ({})",
+            entry.trigger.describe(),
+            entry.profile
+        ));
+    }
+
+    // Keeps the control API's view of the jobs current and restarts the
+    // server when its settings were applied.
+    fn sync_api(&mut self) {
+        *self.api_state.write() = ApiState {
+            jobs: self
+                .settings
+                .profiles
+                .iter()
+                .zip(&self.jobs)
+                .map(|(profile, job)| (profile.name.clone(), job.controls.clone()))
+                .collect(),
+            active: self.settings.active_profile,
+        };
+        if self.api_config.as_ref() != Some(&self.settings.api) {
+            // The old listener has to let go of the port first.
+            self.api_server = None;
+            self.api_server = self
+                .settings
+                .api
+                .enabled
+                .then(|| ApiServer::start(&self.settings.api, self.api_state.clone()));
+            self.api_config = Some(self.settings.api.clone());
+        }
+    }
+
+    fn persist_settings(&mut self) {
+        self.sync_profiles();
+        self.settings.window_filter = self.jobs_panel.window_filter.clone();
+        self.settings.recent_windows = self.jobs_panel.recent.clone();
+        if self.settings != self.saved_settings {
+            self.settings.save().ok();
+            self.saved_settings = self.settings.clone();
         }
     }
 
     fn update_animations(&mut self, delta_time: f32) {
-        if self.running.load(Ordering::SeqCst) {
+        if self.job().controls.running.load(Ordering::SeqCst) {
             self.animation_progress = (self.animation_progress + delta_time * 2.0) % 1.0;
         } else {
             self.animation_progress = 0.0;
         }
-
-        self.windows_list.update_animation(delta_time, self.windows_list.show_windows_list);
     }
 
     fn handle_key_listening(&mut self) {
-        if self.listening_for_key {
-            for vk in 1..255 {
-                let state = unsafe { windows::Win32::UI::Input::KeyboardAndMouse::GetAsyncKeyState(vk as i32) };
-                if (state & 0x1) != 0 && vk != 0x01 {
-                    *self.hotkey_vk.write() = vk as u32;
-                    self.listening_for_key = false;
-                    self.last_key_press = Some(vk as u32);
-                    break;
+        let edges: Vec<KeyEdge> = self.key_events.try_iter().collect();
+        for edge in edges {
+            if let KeyEdge::ButtonPressed { button: MouseButton::Left, x, y } = edge {
+                if self.picking_point {
+                    self.pick_point(x, y);
                 }
+                continue;
+            }
+            let KeyEdge::Pressed(vk) = edge else { continue };
+            if self.macro_panel.listening_for_key {
+                self.settings.macro_hotkey_vk = vk;
+                self.macro_panel.listening_for_key = false;
+                self.last_key_press = Some(vk);
+            } else if self.jobs_panel.listening_for_key {
+                self.settings.profile_hotkey_vk = vk;
+                self.jobs_panel.listening_for_key = false;
+                self.last_key_press = Some(vk);
+            } else if self.settings_panel.listening_for_key {
+                *self.job().hotkey_vk.write() = vk;
+                self.settings_panel.listening_for_key = false;
+                self.last_key_press = Some(vk);
+            } else if vk != 0 && vk == self.settings.profile_hotkey_vk {
+                let next = (self.settings.active_profile + 1) % self.settings.profiles.len();
+                self.apply_job_action(JobAction::Select(next));
+            } else if vk != 0 && vk == self.settings.macro_hotkey_vk {
+                self.toggle_recording();
             }
         }
     }
 
+    // Takes a screen position clicked while in pick mode; clicks outside the
+    // selected window's client area are ignored and picking continues.
+    fn pick_point(&mut self, screen_x: i32, screen_y: i32) {
+        let Some(hwnd) = self.selected_hwnd() else {
+            self.picking_point = false;
+            return;
+        };
+        let Some((x, y)) = WindowManager::screen_to_client(hwnd, screen_x, screen_y) else { return };
+        let Some((width, height)) = WindowManager::client_size(hwnd) else { return };
+        if x < 0 || y < 0 || x >= width || y >= height {
+            return;
+        }
+
+        let controls = self.job().controls.clone();
+        let mut click = controls.click.write();
+        click.point = match click.point {
+            ClickPoint::Pixels { .. } => ClickPoint::Pixels { x, y },
+            ClickPoint::Percent { .. } => ClickPoint::Percent {
+                x: x as f32 * 100.0 / width as f32,
+                y: y as f32 * 100.0 / height as f32,
+            },
+        };
+        self.picking_point = false;
+    }
+
     fn setup_style(&self, ctx: &egui::Context) {
         let mut style = (*ctx.style()).clone();
         style.visuals.widgets.noninteractive.bg_fill = Color32::from_rgb(25, 25, 35);
@@ -121,10 +418,10 @@ impl MyApp {
                 .min_size(egui::vec2(150.0, 35.0)))
                 .clicked()
             {
-                self.windows = WindowManager::get_windows_list();
+                self.refresh_windows();
             }
 
-            let is_running = self.running.load(Ordering::SeqCst);
+            let is_running = self.job().controls.running.load(Ordering::SeqCst);
             let (button_text, button_color) = if is_running {
                 ("⏹ Стоп", Color32::from_rgb(200, 80, 80))
             } else {
@@ -136,15 +433,63 @@ impl MyApp {
                 .min_size(egui::vec2(100.0, 35.0)))
                 .clicked()
             {
-                self.running.store(!is_running, Ordering::SeqCst);
+                self.job().controls.running.store(!is_running, Ordering::SeqCst);
+            }
+        });
+    }
+
+    fn render_click_point(&mut self, ui: &mut egui::Ui) {
+        ui.label(RichText::new("Точка клика:").strong());
+
+        let controls = self.job().controls.clone();
+        let mut click = controls.click.write();
+        ui.horizontal(|ui| {
+            let is_percent = matches!(click.point, ClickPoint::Percent { .. });
+            if ui.selectable_label(!is_percent, "Пиксели").clicked() && is_percent {
+                click.point = ClickPoint::Pixels { x: 0, y: 0 };
+            }
+            if ui.selectable_label(is_percent, "Проценты").clicked() && !is_percent {
+                click.point = ClickPoint::Percent { x: 50.0, y: 50.0 };
+            }
+        });
+
+        ui.horizontal(|ui| {
+            match &mut click.point {
+                ClickPoint::Pixels { x, y } => {
+                    ui.add(egui::DragValue::new(x).range(0..=10000).prefix("X: "));
+                    ui.add(egui::DragValue::new(y).range(0..=10000).prefix("Y: "));
+                }
+                ClickPoint::Percent { x, y } => {
+                    ui.add(egui::DragValue::new(x).range(0.0..=100.0).speed(0.5).prefix("X: ").suffix("%"));
+                    ui.add(egui::DragValue::new(y).range(0.0..=100.0).speed(0.5).prefix("Y: ").suffix("%"));
+                }
+            }
+
+            let (pick_text, pick_color) = if self.picking_point {
+                ("Кликните в окне...", Color32::from_rgb(200, 150, 50))
+            } else {
+                ("🎯 Указать", Color32::from_rgb(70, 100, 180))
+            };
+            let has_target = self.selected_hwnd().is_some();
+            if ui.add_enabled(has_target, egui::Button::new(RichText::new(pick_text).color(Color32::WHITE))
+                .fill(pick_color))
+                .on_disabled_hover_text("Сначала выберите окно")
+                .clicked()
+            {
+                self.picking_point = !self.picking_point;
             }
         });
+
+        ui.add_space(5.0);
+        ui.label(RichText::new("Координаты в клиентской области выбранного окна")
+            .color(Color32::from_rgb(150, 150, 170))
+            .small());
     }
 
     fn render_modifier_buttons(&mut self, ui: &mut egui::Ui) {
         ui.label(RichText::new("Модификаторы:").strong());
 
-        let is_shift_held = self.hold_shift.load(Ordering::SeqCst);
+        let is_shift_held = self.job().controls.hold_shift.load(Ordering::SeqCst);
         let (shift_text, shift_color) = if is_shift_held {
             ("🔒 LShift зажат", Color32::from_rgb(0, 180, 100))
         } else {
@@ -156,13 +501,13 @@ impl MyApp {
             .min_size(egui::vec2(120.0, 35.0)))
             .clicked()
         {
-            self.hold_shift.store(!is_shift_held, Ordering::SeqCst);
+            self.job().controls.hold_shift.store(!is_shift_held, Ordering::SeqCst);
         }
 
         ui.add_space(5.0);
 
         // Кнопка зажатия LCtrl
-        let is_ctrl_held = self.hold_ctrl.load(Ordering::SeqCst);
+        let is_ctrl_held = self.job().controls.hold_ctrl.load(Ordering::SeqCst);
         let (ctrl_text, ctrl_color) = if is_ctrl_held {
             ("🔒 LCtrl зажат", Color32::from_rgb(0, 180, 100))
         } else {
@@ -174,7 +519,7 @@ impl MyApp {
             .min_size(egui::vec2(120.0, 35.0)))
             .clicked()
         {
-            self.hold_ctrl.store(!is_ctrl_held, Ordering::SeqCst);
+            self.job().controls.hold_ctrl.store(!is_ctrl_held, Ordering::SeqCst);
         }
 
         ui.add_space(5.0);
@@ -192,21 +537,29 @@ impl eframe::App for MyApp {
 
         self.update_animations(delta_time);
         self.handle_key_listening();
+        self.run_schedules();
         self.setup_style(ctx);
 
         // Top panel
         egui::TopBottomPanel::top("top_panel")
             .exact_height(40.0)
             .show(ctx, |ui| {
+                let controls = &self.job().controls;
+                let interval = controls.timing.read().describe(*controls.interval_ms.read(), *controls.cps.read());
                 self.top_panel.render(
                     ui,
-                    &self.running,
-                    &self.hotkey_vk,
-                    &self.interval_ms,
-                    self.animation_progress
+                    &self.job().controls,
+                    &self.job().hotkey_vk,
+                    &interval,
+                    self.animation_progress,
+                    &self.settings.profiles[self.settings.active_profile].name,
                 );
             });
 
+        let mut job_action = None;
+        let mut macro_action = None;
+        let mut schedule_action = None;
+
         // Main content
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.vertical_centered(|ui| {
@@ -220,25 +573,84 @@ impl eframe::App for MyApp {
 
             // Main content layout
             ui.columns(2, |columns| {
-                // Left column - Window list dropdown
+                // Left column - Jobs, each with its own window
                 columns[0].group(|ui| {
-                    self.windows_list.render(
+                    job_action = self.jobs_panel.render(
                         ui,
+                        &self.settings.profiles,
+                        &self.jobs,
                         &self.windows,
-                        &self.selected_hwnd
+                        self.settings.active_profile,
+                        self.settings.profile_hotkey_vk,
                     );
                 });
 
+                columns[0].add_space(10.0);
+                columns[0].group(|ui| {
+                    let playing = self.job().controls.playback.read().is_some();
+                    let has_target = self.selected_hwnd().is_some();
+                    macro_action = self.macro_panel.render(
+                        ui,
+                        self.recorder.as_ref().map(MacroRecorder::step_count),
+                        self.recorded_macro.as_ref(),
+                        playing,
+                        self.settings.macro_hotkey_vk,
+                        has_target,
+                    );
+                });
+
+                columns[0].add_space(10.0);
+                columns[0].group(|ui| {
+                    let current_window = self.selected_hwnd()
+                        .and_then(|hwnd| self.windows.iter().find(|window| window.hwnd == hwnd));
+                    schedule_action = self.schedule_panel.render(
+                        ui,
+                        &self.settings.schedules,
+                        &self.settings.profiles,
+                        current_window,
+                        chrono::Local::now().naive_local(),
+                    );
+                });
+
+                columns[0].add_space(10.0);
+                columns[0].group(|ui| {
+                    if let Some(level) = self.log_panel.render(ui, &self.logging, self.settings.log_level) {
+                        self.settings.log_level = level;
+                        self.logging.set_level(level);
+                    }
+                });
+
+                columns[0].add_space(10.0);
+                columns[0].group(|ui| {
+                    let status = self.api_server.as_ref().map(|server| match server {
+                        Ok(server) => Ok(server.address.as_str()),
+                        Err(error) => Err(error.as_str()),
+                    });
+                    if let Some(api) = self.api_panel.render(ui, &self.settings.api, status) {
+                        self.settings.api = api;
+                        self.api_config = None;
+                    }
+                });
+
                 // Right column - Settings
                 columns[1].group(|ui| {
-                    self.settings_panel.render(
+                    let job = &self.jobs[self.settings.active_profile];
+                    if self.settings_panel.render(
                         ui,
-                        &self.hotkey_vk,
-                        &self.interval_ms,
-                        &self.running,
+                        &job.hotkey_vk,
+                        &job.controls,
+                        job.backend_error.as_deref(),
                         self.animation_progress,
-                        &mut self.listening_for_key
-                    );
+                    ) {
+                        let active = self.settings.active_profile;
+                        self.jobs[active].restart();
+                    }
+
+                    ui.add_space(10.0);
+                    ui.separator();
+                    ui.add_space(10.0);
+
+                    self.render_click_point(ui);
 
                     ui.add_space(10.0);
                     ui.separator();
@@ -246,6 +658,16 @@ impl eframe::App for MyApp {
 
                     // Кнопки модификаторов
                     self.render_modifier_buttons(ui);
+
+                    ui.add_space(10.0);
+                    ui.separator();
+                    ui.add_space(10.0);
+
+                    let error = self.job().controls.script_error.read().clone();
+                    let job = &mut self.jobs[self.settings.active_profile];
+                    if self.script_panel.render(ui, &mut job.script_source, &mut job.script_enabled, error.as_deref()) {
+                        job.publish_script();
+                    }
                 });
             });
 
@@ -261,6 +683,25 @@ impl eframe::App for MyApp {
             });
         });
 
+        if let Some(action) = job_action {
+            self.apply_job_action(action);
+        }
+        if let Some(action) = macro_action {
+            self.apply_macro_action(action);
+        }
+        if let Some(action) = schedule_action {
+            self.apply_schedule_action(action);
+        }
+        self.sync_api();
+        self.persist_settings();
         ctx.request_repaint();
     }
-}
\ No newline at end of file
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.persist_settings();
+        self.api_server = None;
+        // Joining the jobs releases any keys a script or modifier still holds.
+        for job in &mut self.jobs {
+            job.shutdown();
+        }
+    }
+}
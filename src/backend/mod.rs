@@ -0,0 +1,116 @@
+#[cfg(windows)]
+pub mod post_message;
+#[cfg(test)]
+pub mod recording;
+#[cfg(target_os = "linux")]
+pub mod uinput;
+#[cfg(target_os = "linux")]
+pub mod x11;
+
+use std::fmt;
+use serde::{Deserialize, Serialize};
+
+pub const VK_LSHIFT: u32 = 0xA0;
+pub const VK_LCONTROL: u32 = 0xA2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum MouseButton {
+    #[default]
+    Left,
+    Right,
+    Middle,
+    X1,
+    X2,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackendError(pub String);
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+pub type BackendResult = Result<(), BackendError>;
+
+/// Delivers synthetic input to a target window. `target` is the same handle
+/// `WindowManager` hands out, coordinates are relative to the client area and
+/// keys are Windows virtual-key codes on every platform.
+pub trait InputBackend: Send {
+    fn mouse_move(&mut self, target: isize, x: i32, y: i32) -> BackendResult;
+    fn mouse_down(&mut self, target: isize, button: MouseButton, x: i32, y: i32) -> BackendResult;
+    fn mouse_up(&mut self, target: isize, button: MouseButton, x: i32, y: i32) -> BackendResult;
+    /// Second press of a double click. Windows delivers it as its own
+    /// `WM_*BUTTONDBLCLK` message; elsewhere it is an ordinary press.
+    fn mouse_double_down(&mut self, target: isize, button: MouseButton, x: i32, y: i32) -> BackendResult {
+        self.mouse_down(target, button, x, y)
+    }
+    fn key_down(&mut self, target: isize, vk: u32) -> BackendResult;
+    fn key_up(&mut self, target: isize, vk: u32) -> BackendResult;
+}
+
+/// Stands in for a backend that could not be set up, failing every send
+/// with the original reason.
+pub struct UnavailableBackend(pub BackendError);
+
+impl InputBackend for UnavailableBackend {
+    fn mouse_move(&mut self, _target: isize, _x: i32, _y: i32) -> BackendResult {
+        Err(self.0.clone())
+    }
+
+    fn mouse_down(&mut self, _target: isize, _button: MouseButton, _x: i32, _y: i32) -> BackendResult {
+        Err(self.0.clone())
+    }
+
+    fn mouse_up(&mut self, _target: isize, _button: MouseButton, _x: i32, _y: i32) -> BackendResult {
+        Err(self.0.clone())
+    }
+
+    fn key_down(&mut self, _target: isize, _vk: u32) -> BackendResult {
+        Err(self.0.clone())
+    }
+
+    fn key_up(&mut self, _target: isize, _vk: u32) -> BackendResult {
+        Err(self.0.clone())
+    }
+}
+
+#[cfg(windows)]
+pub fn platform_backend() -> Result<Box<dyn InputBackend>, BackendError> {
+    Ok(Box::new(post_message::PostMessageBackend::default()))
+}
+
+/// Picks the Linux backend from `BECLICKER_BACKEND` (`x11`, `xtest` or
+/// `uinput`). Without it, Wayland-only sessions get uinput and everything
+/// else gets `XSendEvent`.
+#[cfg(target_os = "linux")]
+pub fn platform_backend() -> Result<Box<dyn InputBackend>, BackendError> {
+    let choice = std::env::var("BECLICKER_BACKEND").unwrap_or_else(|_| {
+        let wayland_only = std::env::var_os("WAYLAND_DISPLAY").is_some()
+            && std::env::var_os("DISPLAY").is_none();
+        if wayland_only { "uinput" } else { "x11" }.to_string()
+    });
+
+    match choice.as_str() {
+        "x11" => Ok(Box::new(x11::X11Backend::new(x11::X11Mode::SendEvent))),
+        "xtest" => Ok(Box::new(x11::X11Backend::new(x11::X11Mode::XTest))),
+        "uinput" => {
+            uinput::UinputBackend::probe()?;
+            let motion = match std::env::var("BECLICKER_UINPUT_SCREEN").ok().and_then(|s| parse_size(&s)) {
+                Some((width, height)) => uinput::UinputMotion::Absolute { width, height },
+                None => uinput::UinputMotion::Relative,
+            };
+            Ok(Box::new(uinput::UinputBackend::open(motion)?))
+        }
+        other => Err(BackendError(format!("unknown BECLICKER_BACKEND '{}'", other))),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn parse_size(s: &str) -> Option<(i32, i32)> {
+    let (w, h) = s.split_once('x')?;
+    Some((w.trim().parse().ok()?, h.trim().parse().ok()?))
+}
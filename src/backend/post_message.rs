@@ -0,0 +1,147 @@
+use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
+use windows::Win32::UI::Input::KeyboardAndMouse::{MapVirtualKeyW, MAPVK_VK_TO_VSC};
+use windows::Win32::UI::WindowsAndMessaging::{
+    PostMessageW, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDBLCLK, WM_LBUTTONDOWN, WM_LBUTTONUP,
+    WM_MBUTTONDBLCLK, WM_MBUTTONDOWN, WM_MBUTTONUP, WM_MOUSEMOVE, WM_RBUTTONDBLCLK,
+    WM_RBUTTONDOWN, WM_RBUTTONUP, WM_XBUTTONDBLCLK, WM_XBUTTONDOWN, WM_XBUTTONUP,
+};
+
+use super::{BackendError, BackendResult, InputBackend, MouseButton};
+
+const MK_LBUTTON: usize = 0x0001;
+const MK_RBUTTON: usize = 0x0002;
+const MK_SHIFT: usize = 0x0004;
+const MK_CONTROL: usize = 0x0008;
+const MK_MBUTTON: usize = 0x0010;
+const MK_XBUTTON1: usize = 0x0020;
+const MK_XBUTTON2: usize = 0x0040;
+const XBUTTON1: usize = 0x0001;
+const XBUTTON2: usize = 0x0002;
+
+/// Posts window messages straight into the target's queue, so the window
+/// does not need focus. The MK_* state in mouse WPARAMs is tracked from what
+/// this backend itself has pressed; the real devices are irrelevant to the
+/// target.
+#[derive(Default)]
+pub struct PostMessageBackend {
+    state: usize,
+}
+
+impl PostMessageBackend {
+    fn post(target: isize, msg: u32, wparam: usize, lparam: isize) -> BackendResult {
+        let hwnd = HWND(target as *mut _);
+        unsafe { PostMessageW(Some(hwnd), msg, WPARAM(wparam), LPARAM(lparam)) }
+            .map_err(|e| BackendError(format!("PostMessageW({:#X}) failed: {}", msg, e)))
+    }
+
+    fn point_lparam(x: i32, y: i32) -> isize {
+        (((y as u16 as u32) << 16) | (x as u16 as u32)) as isize
+    }
+
+    // Window procedures see the generic VK_SHIFT/VK_CONTROL/VK_MENU in WPARAM;
+    // the left/right distinction lives in the scan code and extended bit.
+    fn split_vk(vk: u32) -> (u32, bool) {
+        match vk {
+            0xA0 | 0xA1 => (0x10, false),
+            0xA2 => (0x11, false),
+            0xA3 => (0x11, true),
+            0xA4 => (0x12, false),
+            0xA5 => (0x12, true),
+            _ => (vk, false),
+        }
+    }
+
+    fn button_flag(button: MouseButton) -> usize {
+        match button {
+            MouseButton::Left => MK_LBUTTON,
+            MouseButton::Right => MK_RBUTTON,
+            MouseButton::Middle => MK_MBUTTON,
+            MouseButton::X1 => MK_XBUTTON1,
+            MouseButton::X2 => MK_XBUTTON2,
+        }
+    }
+
+    // (down, double-click, up). Both X buttons share one set of messages and
+    // name the button in the high word of WPARAM.
+    fn button_messages(button: MouseButton) -> (u32, u32, u32) {
+        match button {
+            MouseButton::Left => (WM_LBUTTONDOWN, WM_LBUTTONDBLCLK, WM_LBUTTONUP),
+            MouseButton::Right => (WM_RBUTTONDOWN, WM_RBUTTONDBLCLK, WM_RBUTTONUP),
+            MouseButton::Middle => (WM_MBUTTONDOWN, WM_MBUTTONDBLCLK, WM_MBUTTONUP),
+            MouseButton::X1 | MouseButton::X2 => (WM_XBUTTONDOWN, WM_XBUTTONDBLCLK, WM_XBUTTONUP),
+        }
+    }
+
+    fn button_wparam(&self, button: MouseButton) -> usize {
+        let xbutton = match button {
+            MouseButton::X1 => XBUTTON1 << 16,
+            MouseButton::X2 => XBUTTON2 << 16,
+            _ => 0,
+        };
+        self.state | xbutton
+    }
+
+    fn modifier_flag(vk: u32) -> usize {
+        match vk {
+            0x10 | 0xA0 | 0xA1 => MK_SHIFT,
+            0x11 | 0xA2 | 0xA3 => MK_CONTROL,
+            _ => 0,
+        }
+    }
+
+    // Press messages carry the state including the button being pressed.
+    fn press(&mut self, target: isize, button: MouseButton, msg: u32, x: i32, y: i32) -> BackendResult {
+        self.state |= Self::button_flag(button);
+        Self::post(target, msg, self.button_wparam(button), Self::point_lparam(x, y))
+    }
+
+    fn key_lparam(vk: u32, extended: bool, up: bool) -> isize {
+        let scan = unsafe { MapVirtualKeyW(vk, MAPVK_VK_TO_VSC) } & 0xFF;
+        let mut lparam = 1 | (scan << 16);
+        if extended {
+            lparam |= 1 << 24;
+        }
+        if up {
+            lparam |= (1 << 30) | (1 << 31);
+        }
+        lparam as isize
+    }
+
+    fn key(target: isize, vk: u32, up: bool) -> BackendResult {
+        let (generic, extended) = Self::split_vk(vk);
+        let msg = if up { WM_KEYUP } else { WM_KEYDOWN };
+        Self::post(target, msg, generic as usize, Self::key_lparam(vk, extended, up))
+    }
+}
+
+impl InputBackend for PostMessageBackend {
+    fn mouse_move(&mut self, target: isize, x: i32, y: i32) -> BackendResult {
+        Self::post(target, WM_MOUSEMOVE, self.state, Self::point_lparam(x, y))
+    }
+
+    fn mouse_down(&mut self, target: isize, button: MouseButton, x: i32, y: i32) -> BackendResult {
+        let (msg, _, _) = Self::button_messages(button);
+        self.press(target, button, msg, x, y)
+    }
+
+    fn mouse_double_down(&mut self, target: isize, button: MouseButton, x: i32, y: i32) -> BackendResult {
+        let (_, msg, _) = Self::button_messages(button);
+        self.press(target, button, msg, x, y)
+    }
+
+    fn mouse_up(&mut self, target: isize, button: MouseButton, x: i32, y: i32) -> BackendResult {
+        let (_, _, msg) = Self::button_messages(button);
+        self.state &= !Self::button_flag(button);
+        Self::post(target, msg, self.button_wparam(button), Self::point_lparam(x, y))
+    }
+
+    fn key_down(&mut self, target: isize, vk: u32) -> BackendResult {
+        self.state |= Self::modifier_flag(vk);
+        Self::key(target, vk, false)
+    }
+
+    fn key_up(&mut self, target: isize, vk: u32) -> BackendResult {
+        self.state &= !Self::modifier_flag(vk);
+        Self::key(target, vk, true)
+    }
+}
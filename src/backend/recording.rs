@@ -0,0 +1,82 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use parking_lot::Mutex;
+
+use super::{BackendResult, InputBackend, MouseButton};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEvent {
+    MouseMove { target: isize, x: i32, y: i32 },
+    MouseDown { target: isize, button: MouseButton, x: i32, y: i32 },
+    MouseDoubleDown { target: isize, button: MouseButton, x: i32, y: i32 },
+    MouseUp { target: isize, button: MouseButton, x: i32, y: i32 },
+    KeyDown { target: isize, vk: u32 },
+    KeyUp { target: isize, vk: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordedEvent {
+    /// Time since the backend was created.
+    pub at: Duration,
+    pub event: InputEvent,
+}
+
+/// Keeps every event in memory instead of delivering it. Clones share the
+/// same log, so one copy can be handed to the click thread and the other
+/// inspected afterwards.
+#[derive(Clone)]
+pub struct RecordingBackend {
+    started: Instant,
+    events: Arc<Mutex<Vec<RecordedEvent>>>,
+}
+
+impl RecordingBackend {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            events: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn events(&self) -> Vec<RecordedEvent> {
+        self.events.lock().clone()
+    }
+
+    pub fn trace(&self) -> Vec<InputEvent> {
+        self.events.lock().iter().map(|e| e.event).collect()
+    }
+
+    fn record(&self, event: InputEvent) -> BackendResult {
+        self.events.lock().push(RecordedEvent {
+            at: self.started.elapsed(),
+            event,
+        });
+        Ok(())
+    }
+}
+
+impl InputBackend for RecordingBackend {
+    fn mouse_move(&mut self, target: isize, x: i32, y: i32) -> BackendResult {
+        self.record(InputEvent::MouseMove { target, x, y })
+    }
+
+    fn mouse_down(&mut self, target: isize, button: MouseButton, x: i32, y: i32) -> BackendResult {
+        self.record(InputEvent::MouseDown { target, button, x, y })
+    }
+
+    fn mouse_double_down(&mut self, target: isize, button: MouseButton, x: i32, y: i32) -> BackendResult {
+        self.record(InputEvent::MouseDoubleDown { target, button, x, y })
+    }
+
+    fn mouse_up(&mut self, target: isize, button: MouseButton, x: i32, y: i32) -> BackendResult {
+        self.record(InputEvent::MouseUp { target, button, x, y })
+    }
+
+    fn key_down(&mut self, target: isize, vk: u32) -> BackendResult {
+        self.record(InputEvent::KeyDown { target, vk })
+    }
+
+    fn key_up(&mut self, target: isize, vk: u32) -> BackendResult {
+        self.record(InputEvent::KeyUp { target, vk })
+    }
+}
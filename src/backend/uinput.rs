@@ -0,0 +1,288 @@
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+use super::{BackendError, BackendResult, InputBackend, MouseButton};
+
+const UINPUT_PATH: &str = "/dev/uinput";
+
+const UI_DEV_CREATE: libc::c_ulong = 0x5501;
+const UI_DEV_DESTROY: libc::c_ulong = 0x5502;
+const UI_DEV_SETUP: libc::c_ulong = 0x405C_5503;
+const UI_ABS_SETUP: libc::c_ulong = 0x401C_5504;
+const UI_SET_EVBIT: libc::c_ulong = 0x4004_5564;
+const UI_SET_KEYBIT: libc::c_ulong = 0x4004_5565;
+const UI_SET_RELBIT: libc::c_ulong = 0x4004_5566;
+const UI_SET_ABSBIT: libc::c_ulong = 0x4004_5567;
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+const SYN_REPORT: u16 = 0;
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const BUS_VIRTUAL: u16 = 0x06;
+
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+const BTN_MIDDLE: u16 = 0x112;
+const BTN_SIDE: u16 = 0x113;
+const BTN_EXTRA: u16 = 0x114;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UinputMotion {
+    /// Pointer moves by deltas; click coordinates only matter relative to
+    /// the previous move.
+    Relative,
+    /// Screen-sized absolute axes, so coordinates are screen pixels.
+    Absolute { width: i32, height: i32 },
+}
+
+/// A virtual mouse + keyboard created through `/dev/uinput`. The kernel feeds
+/// it to the compositor like real hardware, which is the only way to inject
+/// input on Wayland. There is no per-window targeting: events go wherever
+/// the focus and pointer are, and `target` is ignored.
+pub struct UinputBackend {
+    device: File,
+    motion: UinputMotion,
+    position: Option<(i32, i32)>,
+}
+
+impl UinputBackend {
+    /// Reports why uinput cannot be used, without creating a device.
+    pub fn probe() -> BackendResult {
+        Self::probe_path(Path::new(UINPUT_PATH))
+    }
+
+    fn probe_path(path: &Path) -> BackendResult {
+        OpenOptions::new().write(true).open(path).map(|_| ()).map_err(|e| Self::open_error(path, e))
+    }
+
+    fn open_error(path: &Path, e: std::io::Error) -> BackendError {
+        let hint = match e.kind() {
+            ErrorKind::NotFound => "load the uinput kernel module (modprobe uinput)",
+            ErrorKind::PermissionDenied => "grant write access with a udev rule or the input group",
+            _ => "check that the uinput device is usable",
+        };
+        BackendError(format!("{} unavailable: {} ({})", path.display(), e, hint))
+    }
+
+    pub fn open(motion: UinputMotion) -> Result<Self, BackendError> {
+        let path = Path::new(UINPUT_PATH);
+        let device = OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(path)
+            .map_err(|e| Self::open_error(path, e))?;
+
+        let fd = device.as_raw_fd();
+        let ioctl = |request: libc::c_ulong, arg: libc::c_ulong| -> BackendResult {
+            if unsafe { libc::ioctl(fd, request, arg) } < 0 {
+                return Err(BackendError(format!(
+                    "uinput ioctl {:#X} failed: {}",
+                    request,
+                    std::io::Error::last_os_error()
+                )));
+            }
+            Ok(())
+        };
+
+        ioctl(UI_SET_EVBIT, EV_KEY as _)?;
+        for code in [BTN_LEFT, BTN_RIGHT, BTN_MIDDLE, BTN_SIDE, BTN_EXTRA] {
+            ioctl(UI_SET_KEYBIT, code as _)?;
+        }
+        for code in (1..256).filter_map(vk_to_evdev) {
+            ioctl(UI_SET_KEYBIT, code as _)?;
+        }
+
+        match motion {
+            UinputMotion::Relative => {
+                ioctl(UI_SET_EVBIT, EV_REL as _)?;
+                ioctl(UI_SET_RELBIT, REL_X as _)?;
+                ioctl(UI_SET_RELBIT, REL_Y as _)?;
+            }
+            UinputMotion::Absolute { width, height } => {
+                ioctl(UI_SET_EVBIT, EV_ABS as _)?;
+                for (code, max) in [(ABS_X, width - 1), (ABS_Y, height - 1)] {
+                    ioctl(UI_SET_ABSBIT, code as _)?;
+                    let mut abs: libc::uinput_abs_setup = unsafe { std::mem::zeroed() };
+                    abs.code = code;
+                    abs.absinfo.maximum = max;
+                    ioctl(UI_ABS_SETUP, &abs as *const _ as _)?;
+                }
+            }
+        }
+
+        let mut setup: libc::uinput_setup = unsafe { std::mem::zeroed() };
+        setup.id.bustype = BUS_VIRTUAL;
+        setup.id.vendor = 0x1209;
+        setup.id.product = 0xBC1C;
+        for (dst, src) in setup.name.iter_mut().zip(b"Be Clicker virtual input") {
+            *dst = *src as libc::c_char;
+        }
+        ioctl(UI_DEV_SETUP, &setup as *const _ as _)?;
+        ioctl(UI_DEV_CREATE, 0)?;
+
+        Ok(Self { device, motion, position: None })
+    }
+
+    fn emit(&mut self, events: &[(u16, u16, i32)]) -> BackendResult {
+        let mut buf = Vec::with_capacity((events.len() + 1) * std::mem::size_of::<libc::input_event>());
+        for &(type_, code, value) in events.iter().chain([(EV_SYN, SYN_REPORT, 0)].iter()) {
+            let mut event: libc::input_event = unsafe { std::mem::zeroed() };
+            event.type_ = type_;
+            event.code = code;
+            event.value = value;
+            let bytes = unsafe {
+                std::slice::from_raw_parts(
+                    &event as *const _ as *const u8,
+                    std::mem::size_of::<libc::input_event>(),
+                )
+            };
+            buf.extend_from_slice(bytes);
+        }
+        self.device
+            .write_all(&buf)
+            .map_err(|e| BackendError(format!("uinput write failed: {}", e)))
+    }
+
+    fn move_to(&mut self, x: i32, y: i32) -> BackendResult {
+        if self.position == Some((x, y)) {
+            return Ok(());
+        }
+        let previous = self.position.replace((x, y));
+        match self.motion {
+            UinputMotion::Absolute { .. } => self.emit(&[(EV_ABS, ABS_X, x), (EV_ABS, ABS_Y, y)]),
+            // The first position only becomes the reference point.
+            UinputMotion::Relative => match previous {
+                Some((px, py)) => self.emit(&[(EV_REL, REL_X, x - px), (EV_REL, REL_Y, y - py)]),
+                None => Ok(()),
+            },
+        }
+    }
+
+    fn button_code(button: MouseButton) -> u16 {
+        match button {
+            MouseButton::Left => BTN_LEFT,
+            MouseButton::Right => BTN_RIGHT,
+            MouseButton::Middle => BTN_MIDDLE,
+            MouseButton::X1 => BTN_SIDE,
+            MouseButton::X2 => BTN_EXTRA,
+        }
+    }
+
+    fn key(&mut self, vk: u32, value: i32) -> BackendResult {
+        let code = vk_to_evdev(vk).ok_or_else(|| BackendError(format!("no evdev key for VK {:#X}", vk)))?;
+        self.emit(&[(EV_KEY, code, value)])
+    }
+}
+
+impl InputBackend for UinputBackend {
+    fn mouse_move(&mut self, _target: isize, x: i32, y: i32) -> BackendResult {
+        self.move_to(x, y)
+    }
+
+    fn mouse_down(&mut self, _target: isize, button: MouseButton, x: i32, y: i32) -> BackendResult {
+        self.move_to(x, y)?;
+        self.emit(&[(EV_KEY, Self::button_code(button), 1)])
+    }
+
+    fn mouse_up(&mut self, _target: isize, button: MouseButton, x: i32, y: i32) -> BackendResult {
+        self.move_to(x, y)?;
+        self.emit(&[(EV_KEY, Self::button_code(button), 0)])
+    }
+
+    fn key_down(&mut self, _target: isize, vk: u32) -> BackendResult {
+        self.key(vk, 1)
+    }
+
+    fn key_up(&mut self, _target: isize, vk: u32) -> BackendResult {
+        self.key(vk, 0)
+    }
+}
+
+impl Drop for UinputBackend {
+    fn drop(&mut self) {
+        unsafe {
+            libc::ioctl(self.device.as_raw_fd(), UI_DEV_DESTROY);
+        }
+    }
+}
+
+/// Linux input event codes (`KEY_*`) for the virtual keys the app uses.
+fn vk_to_evdev(vk: u32) -> Option<u16> {
+    const LETTERS: [u16; 26] = [
+        30, 48, 46, 32, 18, 33, 34, 35, 23, 36, 37, 38, 50, 49, 24, 25, 16, 19, 31, 20, 22, 47,
+        17, 45, 21, 44,
+    ];
+    const NUMPAD: [u16; 10] = [82, 79, 80, 81, 75, 76, 77, 71, 72, 73];
+    const F_KEYS: [u16; 12] = [59, 60, 61, 62, 63, 64, 65, 66, 67, 68, 87, 88];
+
+    let code = match vk {
+        0x08 => 14,
+        0x09 => 15,
+        0x0D => 28,
+        0x10 | 0xA0 => 42,
+        0xA1 => 54,
+        0x11 | 0xA2 => 29,
+        0xA3 => 97,
+        0x12 | 0xA4 => 56,
+        0xA5 => 100,
+        0x13 => 119,
+        0x14 => 58,
+        0x1B => 1,
+        0x20 => 57,
+        0x21 => 104,
+        0x22 => 109,
+        0x23 => 107,
+        0x24 => 102,
+        0x25 => 105,
+        0x26 => 103,
+        0x27 => 106,
+        0x28 => 108,
+        0x2D => 110,
+        0x2E => 111,
+        0x30 => 11,
+        0x31..=0x39 => 2 + (vk - 0x31) as u16,
+        0x41..=0x5A => LETTERS[(vk - 0x41) as usize],
+        0x60..=0x69 => NUMPAD[(vk - 0x60) as usize],
+        0x6A => 55,
+        0x6B => 78,
+        0x6C => 121,
+        0x6D => 74,
+        0x6E => 83,
+        0x6F => 98,
+        0x70..=0x7B => F_KEYS[(vk - 0x70) as usize],
+        0x90 => 69,
+        0x91 => 70,
+        _ => return None,
+    };
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modifiers_and_hotkeys_map_to_evdev_codes() {
+        assert_eq!(vk_to_evdev(0xA0), Some(42));
+        assert_eq!(vk_to_evdev(0xA2), Some(29));
+        assert_eq!(vk_to_evdev(0x75), Some(64));
+        assert_eq!(vk_to_evdev(0x52), Some(19));
+        assert_eq!(vk_to_evdev(0x30), Some(11));
+        assert_eq!(vk_to_evdev(0x39), Some(10));
+        assert_eq!(vk_to_evdev(0xFF), None);
+    }
+
+    #[test]
+    fn probe_explains_missing_device() {
+        let err = UinputBackend::probe_path(Path::new("/nonexistent/uinput")).unwrap_err();
+        assert!(err.0.contains("modprobe uinput"), "{}", err);
+    }
+}
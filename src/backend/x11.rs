@@ -0,0 +1,478 @@
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{
+    ButtonPressEvent, ConnectionExt as _, EventMask, KeyButMask, KeyPressEvent, MotionNotifyEvent,
+    Motion, Window, BUTTON_PRESS_EVENT, BUTTON_RELEASE_EVENT, KEY_PRESS_EVENT, KEY_RELEASE_EVENT,
+    MOTION_NOTIFY_EVENT,
+};
+use x11rb::protocol::xtest::ConnectionExt as _;
+use x11rb::rust_connection::RustConnection;
+use x11rb::CURRENT_TIME;
+use x11rb::NONE;
+
+use super::{BackendError, BackendResult, InputBackend, MouseButton};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum X11Mode {
+    /// `XSendEvent` straight to the target window. Works in the background,
+    /// but the events carry the `send_event` flag and some clients drop them.
+    SendEvent,
+    /// XTest fake input. Indistinguishable from real input, but it moves the
+    /// real pointer and goes to whatever window is under it or focused.
+    XTest,
+}
+
+pub struct X11Backend {
+    display: Option<String>,
+    mode: X11Mode,
+    conn: Option<X11Connection>,
+    state: KeyButMask,
+}
+
+struct X11Connection {
+    conn: RustConnection,
+    root: Window,
+    keymap: Keymap,
+}
+
+impl X11Backend {
+    /// Connects lazily on first use, so a missing X server shows up as a
+    /// failed send instead of a panic at startup.
+    pub fn new(mode: X11Mode) -> Self {
+        Self::with_display(None, mode)
+    }
+
+    pub fn with_display(display: Option<&str>, mode: X11Mode) -> Self {
+        Self {
+            display: display.map(str::to_owned),
+            mode,
+            conn: None,
+            state: KeyButMask::default(),
+        }
+    }
+
+    fn connection(&mut self) -> Result<&X11Connection, BackendError> {
+        if self.conn.is_none() {
+            let (conn, screen) = x11rb::connect(self.display.as_deref()).map_err(x11_error)?;
+            let root = conn.setup().roots[screen].root;
+            let keymap = Keymap::load(&conn)?;
+            self.conn = Some(X11Connection { conn, root, keymap });
+        }
+        Ok(self.conn.as_ref().unwrap())
+    }
+
+    fn button_detail(button: MouseButton) -> u8 {
+        match button {
+            MouseButton::Left => 1,
+            MouseButton::Middle => 2,
+            MouseButton::Right => 3,
+            MouseButton::X1 => 8,
+            MouseButton::X2 => 9,
+        }
+    }
+
+    fn button_mask(button: MouseButton) -> KeyButMask {
+        match button {
+            MouseButton::Left => KeyButMask::BUTTON1,
+            MouseButton::Middle => KeyButMask::BUTTON2,
+            MouseButton::Right => KeyButMask::BUTTON3,
+            // The core protocol has no state bits beyond button 5.
+            MouseButton::X1 | MouseButton::X2 => KeyButMask::default(),
+        }
+    }
+
+    fn modifier_mask(vk: u32) -> KeyButMask {
+        match vk {
+            0x10 | 0xA0 | 0xA1 => KeyButMask::SHIFT,
+            0x11 | 0xA2 | 0xA3 => KeyButMask::CONTROL,
+            0x12 | 0xA4 | 0xA5 => KeyButMask::MOD1,
+            _ => KeyButMask::default(),
+        }
+    }
+
+    fn send_pointer(&mut self, target: isize, response_type: u8, detail: u8, x: i32, y: i32) -> BackendResult {
+        let state = self.state;
+        let mode = self.mode;
+        let c = self.connection()?;
+        let window = target as Window;
+        let (x, y) = (clamp_i16(x), clamp_i16(y));
+        let translated = c
+            .conn
+            .translate_coordinates(window, c.root, x, y)
+            .map_err(x11_error)?
+            .reply()
+            .map_err(x11_error)?;
+
+        match mode {
+            X11Mode::SendEvent => {
+                let (event_mask, event) = if response_type == MOTION_NOTIFY_EVENT {
+                    let event = MotionNotifyEvent {
+                        response_type,
+                        detail: Motion::NORMAL,
+                        sequence: 0,
+                        time: CURRENT_TIME,
+                        root: c.root,
+                        event: window,
+                        child: NONE,
+                        root_x: translated.dst_x,
+                        root_y: translated.dst_y,
+                        event_x: x,
+                        event_y: y,
+                        state,
+                        same_screen: true,
+                    };
+                    (EventMask::POINTER_MOTION, <[u8; 32]>::from(event))
+                } else {
+                    let event = ButtonPressEvent {
+                        response_type,
+                        detail,
+                        sequence: 0,
+                        time: CURRENT_TIME,
+                        root: c.root,
+                        event: window,
+                        child: NONE,
+                        root_x: translated.dst_x,
+                        root_y: translated.dst_y,
+                        event_x: x,
+                        event_y: y,
+                        state,
+                        same_screen: true,
+                    };
+                    let mask = if response_type == BUTTON_PRESS_EVENT {
+                        EventMask::BUTTON_PRESS
+                    } else {
+                        EventMask::BUTTON_RELEASE
+                    };
+                    (mask, <[u8; 32]>::from(event))
+                };
+                c.conn.send_event(true, window, event_mask, event).map_err(x11_error)?;
+            }
+            X11Mode::XTest => {
+                c.conn
+                    .xtest_fake_input(MOTION_NOTIFY_EVENT, 0, CURRENT_TIME, c.root, translated.dst_x, translated.dst_y, 0)
+                    .map_err(x11_error)?;
+                if response_type != MOTION_NOTIFY_EVENT {
+                    c.conn
+                        .xtest_fake_input(response_type, detail, CURRENT_TIME, NONE, 0, 0, 0)
+                        .map_err(x11_error)?;
+                }
+            }
+        }
+        c.conn.flush().map_err(x11_error)
+    }
+
+    fn send_key(&mut self, target: isize, response_type: u8, vk: u32) -> BackendResult {
+        let state = self.state;
+        let mode = self.mode;
+        let c = self.connection()?;
+        let keycode = c
+            .keymap
+            .keycode(vk)
+            .ok_or_else(|| BackendError(format!("no X keycode for VK {:#X}", vk)))?;
+        let window = target as Window;
+
+        match mode {
+            X11Mode::SendEvent => {
+                let event = KeyPressEvent {
+                    response_type,
+                    detail: keycode,
+                    sequence: 0,
+                    time: CURRENT_TIME,
+                    root: c.root,
+                    event: window,
+                    child: NONE,
+                    root_x: 0,
+                    root_y: 0,
+                    event_x: 0,
+                    event_y: 0,
+                    state,
+                    same_screen: true,
+                };
+                let mask = if response_type == KEY_PRESS_EVENT {
+                    EventMask::KEY_PRESS
+                } else {
+                    EventMask::KEY_RELEASE
+                };
+                c.conn.send_event(true, window, mask, event).map_err(x11_error)?;
+            }
+            X11Mode::XTest => {
+                c.conn
+                    .xtest_fake_input(response_type, keycode, CURRENT_TIME, NONE, 0, 0, 0)
+                    .map_err(x11_error)?;
+            }
+        }
+        c.conn.flush().map_err(x11_error)
+    }
+}
+
+impl InputBackend for X11Backend {
+    fn mouse_move(&mut self, target: isize, x: i32, y: i32) -> BackendResult {
+        self.send_pointer(target, MOTION_NOTIFY_EVENT, 0, x, y)
+    }
+
+    fn mouse_down(&mut self, target: isize, button: MouseButton, x: i32, y: i32) -> BackendResult {
+        // The press event carries the state from before the press.
+        self.send_pointer(target, BUTTON_PRESS_EVENT, Self::button_detail(button), x, y)?;
+        self.state |= Self::button_mask(button);
+        Ok(())
+    }
+
+    fn mouse_up(&mut self, target: isize, button: MouseButton, x: i32, y: i32) -> BackendResult {
+        let result = self.send_pointer(target, BUTTON_RELEASE_EVENT, Self::button_detail(button), x, y);
+        self.state = self.state.remove(Self::button_mask(button));
+        result
+    }
+
+    fn key_down(&mut self, target: isize, vk: u32) -> BackendResult {
+        self.send_key(target, KEY_PRESS_EVENT, vk)?;
+        self.state |= Self::modifier_mask(vk);
+        Ok(())
+    }
+
+    fn key_up(&mut self, target: isize, vk: u32) -> BackendResult {
+        let result = self.send_key(target, KEY_RELEASE_EVENT, vk);
+        self.state = self.state.remove(Self::modifier_mask(vk));
+        result
+    }
+}
+
+/// Keysym to keycode lookup for the server's current keyboard mapping.
+pub struct Keymap {
+    min_keycode: u8,
+    keysyms_per_keycode: usize,
+    keysyms: Vec<u32>,
+}
+
+impl Keymap {
+    pub fn load(conn: &RustConnection) -> Result<Self, BackendError> {
+        let setup = conn.setup();
+        let min_keycode = setup.min_keycode;
+        let count = setup.max_keycode - min_keycode + 1;
+        let reply = conn
+            .get_keyboard_mapping(min_keycode, count)
+            .map_err(x11_error)?
+            .reply()
+            .map_err(x11_error)?;
+        Ok(Self {
+            min_keycode,
+            keysyms_per_keycode: reply.keysyms_per_keycode as usize,
+            keysyms: reply.keysyms,
+        })
+    }
+
+    pub fn keycode(&self, vk: u32) -> Option<u8> {
+        let keysym = vk_to_keysym(vk)?;
+        self.keysyms
+            .chunks(self.keysyms_per_keycode.max(1))
+            .position(|syms| syms.contains(&keysym))
+            .map(|i| self.min_keycode + i as u8)
+    }
+}
+
+pub fn vk_to_keysym(vk: u32) -> Option<u32> {
+    let keysym = match vk {
+        0x08 => 0xFF08,
+        0x09 => 0xFF09,
+        0x0D => 0xFF0D,
+        0x10 | 0xA0 => 0xFFE1,
+        0xA1 => 0xFFE2,
+        0x11 | 0xA2 => 0xFFE3,
+        0xA3 => 0xFFE4,
+        0x12 | 0xA4 => 0xFFE9,
+        0xA5 => 0xFFEA,
+        0x13 => 0xFF13,
+        0x14 => 0xFFE5,
+        0x1B => 0xFF1B,
+        0x20 => 0x20,
+        0x21 => 0xFF55,
+        0x22 => 0xFF56,
+        0x23 => 0xFF57,
+        0x24 => 0xFF50,
+        0x25 => 0xFF51,
+        0x26 => 0xFF52,
+        0x27 => 0xFF53,
+        0x28 => 0xFF54,
+        0x2D => 0xFF63,
+        0x2E => 0xFFFF,
+        0x30..=0x39 => vk,
+        // Keysyms for letters are the lowercase ASCII codes.
+        0x41..=0x5A => vk + 0x20,
+        0x60..=0x69 => 0xFFB0 + (vk - 0x60),
+        0x6A => 0xFFAA,
+        0x6B => 0xFFAB,
+        0x6C => 0xFFAC,
+        0x6D => 0xFFAD,
+        0x6E => 0xFFAE,
+        0x6F => 0xFFAF,
+        0x70..=0x87 => 0xFFBE + (vk - 0x70),
+        0x90 => 0xFF7F,
+        0x91 => 0xFF14,
+        _ => return None,
+    };
+    Some(keysym)
+}
+
+fn clamp_i16(v: i32) -> i16 {
+    v.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+}
+
+fn x11_error(e: impl std::fmt::Display) -> BackendError {
+    BackendError(format!("X11: {}", e))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::process::{Child, Command, Stdio};
+    use std::time::{Duration, Instant};
+    use x11rb::protocol::xproto::{CreateWindowAux, WindowClass};
+    use x11rb::protocol::Event;
+    use x11rb::COPY_DEPTH_FROM_PARENT;
+
+    /// A private Xvfb server for one test. Returns `None` when Xvfb is not
+    /// installed so the suite still passes on machines without it.
+    pub(crate) struct Xvfb {
+        child: Child,
+        pub display: String,
+    }
+
+    impl Xvfb {
+        pub(crate) fn start() -> Option<Self> {
+            static NEXT: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+            let n = 90 + (std::process::id() % 100) * 10
+                + NEXT.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let display = format!(":{}", n);
+            let child = Command::new("Xvfb")
+                .args([display.as_str(), "-screen", "0", "800x600x24", "-nolisten", "tcp"])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+            let xvfb = Self { child, display };
+
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while Instant::now() < deadline {
+                if x11rb::connect(Some(&xvfb.display)).is_ok() {
+                    return Some(xvfb);
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            None
+        }
+    }
+
+    impl Drop for Xvfb {
+        fn drop(&mut self) {
+            self.child.kill().ok();
+            self.child.wait().ok();
+        }
+    }
+
+    fn input_window(conn: &RustConnection, screen: usize) -> Window {
+        let root = conn.setup().roots[screen].root;
+        let window = conn.generate_id().unwrap();
+        let mask = EventMask::BUTTON_PRESS
+            | EventMask::BUTTON_RELEASE
+            | EventMask::KEY_PRESS
+            | EventMask::KEY_RELEASE;
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            10,
+            20,
+            200,
+            100,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            0,
+            &CreateWindowAux::new().event_mask(mask),
+        )
+        .unwrap();
+        conn.map_window(window).unwrap();
+        conn.flush().unwrap();
+        window
+    }
+
+    fn next_event(conn: &RustConnection) -> Event {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        loop {
+            if let Some(event) = conn.poll_for_event().unwrap() {
+                return event;
+            }
+            assert!(Instant::now() < deadline, "timed out waiting for an X event");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn vk_to_keysym_covers_hotkey_labels() {
+        assert_eq!(vk_to_keysym(0x75), Some(0xFFC3));
+        assert_eq!(vk_to_keysym(0x52), Some(u32::from(b'r')));
+        assert_eq!(vk_to_keysym(0xA0), Some(0xFFE1));
+        assert_eq!(vk_to_keysym(0xFF), None);
+    }
+
+    #[test]
+    fn send_event_reaches_background_window() {
+        let Some(xvfb) = Xvfb::start() else {
+            eprintln!("Xvfb not found, skipping");
+            return;
+        };
+        let (conn, screen) = x11rb::connect(Some(&xvfb.display)).unwrap();
+        let window = input_window(&conn, screen);
+        let target = window as isize;
+
+        let mut backend = X11Backend::with_display(Some(&xvfb.display), X11Mode::SendEvent);
+        backend.key_down(target, 0xA0).unwrap();
+        backend.mouse_down(target, MouseButton::Left, 15, 25).unwrap();
+        backend.mouse_up(target, MouseButton::Left, 15, 25).unwrap();
+        backend.key_up(target, 0xA0).unwrap();
+
+        let shift = Keymap::load(&conn).unwrap().keycode(0xA0).unwrap();
+        match next_event(&conn) {
+            Event::KeyPress(e) => assert_eq!((e.event, e.detail), (window, shift)),
+            other => panic!("unexpected {:?}", other),
+        }
+        match next_event(&conn) {
+            Event::ButtonPress(e) => {
+                assert_eq!((e.event, e.detail, e.event_x, e.event_y), (window, 1, 15, 25));
+                assert!(e.state.contains(KeyButMask::SHIFT));
+                assert_eq!((e.root_x, e.root_y), (25, 45));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match next_event(&conn) {
+            Event::ButtonRelease(e) => assert!(e.state.contains(KeyButMask::BUTTON1)),
+            other => panic!("unexpected {:?}", other),
+        }
+        match next_event(&conn) {
+            Event::KeyRelease(e) => assert_eq!(e.detail, shift),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn xtest_clicks_window_under_pointer() {
+        let Some(xvfb) = Xvfb::start() else {
+            eprintln!("Xvfb not found, skipping");
+            return;
+        };
+        let (conn, screen) = x11rb::connect(Some(&xvfb.display)).unwrap();
+        let window = input_window(&conn, screen);
+
+        let mut backend = X11Backend::with_display(Some(&xvfb.display), X11Mode::XTest);
+        backend.mouse_down(window as isize, MouseButton::Right, 50, 50).unwrap();
+        backend.mouse_up(window as isize, MouseButton::Right, 50, 50).unwrap();
+
+        match next_event(&conn) {
+            Event::ButtonPress(e) => assert_eq!((e.event, e.detail, e.event_x, e.event_y), (window, 3, 50, 50)),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn missing_display_is_reported_as_error() {
+        let mut backend = X11Backend::with_display(Some(":4242"), X11Mode::SendEvent);
+        assert!(backend.mouse_down(1, MouseButton::Left, 0, 0).is_err());
+    }
+}
@@ -0,0 +1,413 @@
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use std::time::Duration;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use parking_lot::RwLock;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::backend::{self, MouseButton};
+use crate::clicker::{ClickAction, ClickCount, ClickPoint, Clicker, ClickerControls};
+use crate::hotkey_manager::HotkeyManager;
+use crate::session::{StopReason, StopRules};
+use crate::timing::CPS_RANGE;
+use crate::window_manager::{WindowInfo, WindowManager};
+
+/// No window matched `--window-title` / `--window-id`.
+pub const EXIT_WINDOW_NOT_FOUND: i32 = 3;
+/// No input backend could be opened on this system.
+pub const EXIT_BACKEND_UNAVAILABLE: i32 = 4;
+/// A post to the window failed, e.g. because it was closed mid-run.
+pub const EXIT_POST_FAILED: i32 = 5;
+
+// Set by Ctrl+C, SIGTERM or a closing console; the headless run stops on it.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+// Set once a stopped run has let go of its held input.
+static RELEASED: AtomicBool = AtomicBool::new(false);
+
+/// Without a subcommand the window opens as usual.
+#[derive(Parser)]
+#[command(name = "autoclick", version, about = "Background auto-clicker")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Clicks into a window until a limit is reached or the stop key is pressed.
+    Run(RunArgs),
+    /// Lists the windows that can be targeted.
+    Windows {
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    /// Exact title, or failing that the first title containing it.
+    #[arg(long, required_unless_present = "window_id")]
+    window_title: Option<String>,
+    /// Only windows of this executable, e.g. `chrome.exe`.
+    #[arg(long)]
+    process: Option<String>,
+    /// Only windows of this class.
+    #[arg(long)]
+    class: Option<String>,
+    /// Handle as printed by `autoclick windows`.
+    #[arg(long, conflicts_with = "window_title")]
+    window_id: Option<isize>,
+    /// Pause between clicks in ms.
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u64).range(1..))]
+    interval: u64,
+    /// Clicks per second, 0.5 to 1000; overrides `--interval`.
+    #[arg(long, value_parser = parse_cps)]
+    cps: Option<f64>,
+    #[arg(long, value_enum, default_value_t = Button::Left)]
+    button: Button,
+    #[arg(long = "click", value_enum, default_value_t = Kind::Single)]
+    kind: Kind,
+    /// Client-area point in pixels, e.g. `--at 120,40`.
+    #[arg(long, value_parser = parse_point)]
+    at: Option<(i32, i32)>,
+    /// Stop after this many clicks.
+    #[arg(long)]
+    count: Option<u64>,
+    /// Stop after this many seconds.
+    #[arg(long)]
+    duration: Option<u64>,
+    /// Modifier held while clicking; may be given twice.
+    #[arg(long, value_enum)]
+    hold: Vec<Modifier>,
+    /// Key that stops the run early, e.g. F6.
+    #[arg(long, value_parser = parse_key)]
+    stop_key: Option<u32>,
+    /// Keep clicking when posts fail instead of stopping; the exit code
+    /// still reports them.
+    #[arg(long)]
+    keep_going: bool,
+    /// Print the summary as JSON.
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Button {
+    Left,
+    Right,
+    Middle,
+    X1,
+    X2,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Kind {
+    Single,
+    Double,
+    Triple,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Modifier {
+    Shift,
+    Ctrl,
+}
+
+fn parse_point(text: &str) -> Result<(i32, i32), String> {
+    let (x, y) = text.split_once(',').ok_or("expected X,Y")?;
+    let coordinate = |value: &str| value.trim().parse::<i32>().map_err(|e| e.to_string());
+    Ok((coordinate(x)?, coordinate(y)?))
+}
+
+// Same range as the CPS slider.
+fn parse_cps(text: &str) -> Result<f64, String> {
+    let cps = text.trim().parse::<f64>().map_err(|e| e.to_string())?;
+    if CPS_RANGE.contains(&cps) {
+        Ok(cps)
+    } else {
+        Err(format!("must be between {} and {}", CPS_RANGE.start(), CPS_RANGE.end()))
+    }
+}
+
+// Key names as the UI shows them, e.g. "F6" or "page down".
+fn parse_key(text: &str) -> Result<u32, String> {
+    (1..=0xFE)
+        .find(|vk| HotkeyManager::vk_to_key_name(*vk).eq_ignore_ascii_case(text.trim()))
+        .ok_or_else(|| format!("unknown key {:?}", text))
+}
+
+#[derive(Serialize)]
+pub struct WindowEntry<'a> {
+    id: isize,
+    title: &'a str,
+    class: Option<&'a str>,
+    pid: Option<u32>,
+    process: Option<&'a str>,
+    exe_path: Option<String>,
+    client_size: Option<(i32, i32)>,
+    minimized: bool,
+    visible: bool,
+    tool: bool,
+    owner: Option<isize>,
+}
+
+impl<'a> From<&'a WindowInfo> for WindowEntry<'a> {
+    fn from(window: &'a WindowInfo) -> Self {
+        Self {
+            id: window.hwnd,
+            title: &window.title,
+            class: window.class.as_deref(),
+            pid: window.pid,
+            process: window.process.as_deref(),
+            exe_path: window.exe_path.as_ref().map(|path| path.display().to_string()),
+            client_size: window.client_size,
+            minimized: window.minimized,
+            visible: window.visible,
+            tool: window.tool,
+            owner: window.owner,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Summary {
+    clicks: u64,
+    failed: u64,
+    elapsed_secs: f64,
+    measured_cps: f64,
+    stopped_by: &'static str,
+}
+
+/// Runs a subcommand and returns the process exit code.
+pub fn run(command: Command) -> i32 {
+    match command {
+        Command::Windows { json } => list_windows(json),
+        Command::Run(args) => run_clicker(args),
+    }
+}
+
+fn list_windows(json: bool) -> i32 {
+    let windows = WindowManager::get_windows_list();
+    if json {
+        let entries: Vec<_> = windows.iter().map(WindowEntry::from).collect();
+        println!("{}", serde_json::to_string_pretty(&entries).unwrap_or_default());
+    } else {
+        for window in &windows {
+            let pid = window.pid.map(|pid| pid.to_string()).unwrap_or_default();
+            println!("{}\t{}\t{}\t{}", window.hwnd, pid, window.process.as_deref().unwrap_or(""), window.title);
+        }
+    }
+    0
+}
+
+fn find_window(args: &RunArgs, windows: &[WindowInfo]) -> Option<isize> {
+    if let Some(id) = args.window_id {
+        return windows.iter().any(|window| window.hwnd == id).then_some(id);
+    }
+    let wanted = args.window_title.as_deref()?;
+    let candidates = windows.iter().filter(|window| {
+        args.process.as_deref().is_none_or(|process| window.process.as_deref().is_some_and(|p| p.eq_ignore_ascii_case(process)))
+            && args.class.as_deref().is_none_or(|class| window.class.as_deref() == Some(class))
+    });
+    let candidates: Vec<_> = candidates.collect();
+    candidates
+        .iter()
+        .find(|window| window.title == wanted)
+        .or_else(|| candidates.iter().find(|window| window.title.contains(wanted)))
+        .map(|window| window.hwnd)
+}
+
+fn run_clicker(args: RunArgs) -> i32 {
+    let Some(hwnd) = find_window(&args, &WindowManager::get_windows_list()) else {
+        eprintln!("window not found");
+        return EXIT_WINDOW_NOT_FOUND;
+    };
+    let input_backend = match backend::platform_backend() {
+        Ok(input_backend) => input_backend,
+        Err(e) => {
+            eprintln!("input unavailable: {}", e);
+            return EXIT_BACKEND_UNAVAILABLE;
+        }
+    };
+
+    let controls = ClickerControls::default();
+    *controls.selected_hwnd.write() = Some(hwnd);
+    *controls.interval_ms.write() = args.interval;
+    *controls.cps.write() = args.cps;
+    *controls.click.write() = ClickAction {
+        button: match args.button {
+            Button::Left => MouseButton::Left,
+            Button::Right => MouseButton::Right,
+            Button::Middle => MouseButton::Middle,
+            Button::X1 => MouseButton::X1,
+            Button::X2 => MouseButton::X2,
+        },
+        count: match args.kind {
+            Kind::Single => ClickCount::Single,
+            Kind::Double => ClickCount::Double,
+            Kind::Triple => ClickCount::Triple,
+        },
+        point: args.at.map(|(x, y)| ClickPoint::Pixels { x, y }).unwrap_or_default(),
+    };
+    *controls.stop_rules.write() = StopRules {
+        max_clicks: args.count,
+        max_duration_secs: args.duration,
+        stop_at: None,
+    };
+    controls.hold_shift.store(args.hold.contains(&Modifier::Shift), Ordering::SeqCst);
+    controls.hold_ctrl.store(args.hold.contains(&Modifier::Ctrl), Ordering::SeqCst);
+    controls.running.store(true, Ordering::SeqCst);
+
+    // The global key hook is only set up when asked for; CI machines often
+    // have nothing to hook.
+    let _hotkey = args
+        .stop_key
+        .map(|vk| HotkeyManager::start_hotkey_listener(Arc::new(RwLock::new(vk)), controls.running.clone()));
+    let mut clicker = Clicker::start_clicker(input_backend, controls.clone());
+    catch_interrupts();
+    info!(hwnd, "headless run started");
+
+    let (mut post_failed, mut interrupted) = (false, false);
+    while controls.running.load(Ordering::SeqCst) {
+        std::thread::sleep(Duration::from_millis(50));
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            info!(hwnd, "interrupted, stopping headless run");
+            interrupted = true;
+            controls.running.store(false, Ordering::SeqCst);
+        } else if !args.keep_going && controls.stats.read().failed > 0 {
+            warn!(hwnd, "post failed, stopping headless run");
+            post_failed = true;
+            controls.running.store(false, Ordering::SeqCst);
+        }
+    }
+    // Joining releases held modifiers before the summary is printed.
+    clicker.stop();
+    RELEASED.store(true, Ordering::SeqCst);
+
+    let stats = controls.stats.read().clone();
+    let status = *controls.session.read();
+    let summary = Summary {
+        clicks: stats.clicks,
+        failed: stats.failed,
+        elapsed_secs: status.elapsed.as_secs_f64(),
+        measured_cps: stats.measured_cps,
+        stopped_by: match status.stopped {
+            _ if post_failed => "post_failed",
+            _ if interrupted => "interrupted",
+            Some(StopReason::ClickLimit) => "click_limit",
+            Some(StopReason::TimeLimit) => "time_limit",
+            Some(StopReason::StopTime) => "stop_time",
+            None => "stop_key",
+        },
+    };
+    if args.json {
+        println!("{}", serde_json::to_string(&summary).unwrap_or_default());
+    } else {
+        println!(
+            "{} clicks, {} failed, {:.1} s, {:.1} CPS, stopped by {}",
+            summary.clicks, summary.failed, summary.elapsed_secs, summary.measured_cps, summary.stopped_by,
+        );
+    }
+
+    if stats.failed > 0 { EXIT_POST_FAILED } else { 0 }
+}
+
+// The release build has no console of its own; borrow the one the command
+// was started from so output and exit codes behave like any CLI tool.
+// Ctrl+C, SIGTERM and closing the console stop the run like the stop key
+// does, instead of killing the process with modifiers still held down.
+fn catch_interrupts() {
+    #[cfg(target_os = "linux")]
+    unsafe {
+        extern "C" fn on_signal(_: libc::c_int) {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+        }
+        let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        libc::signal(libc::SIGINT, handler);
+        libc::signal(libc::SIGTERM, handler);
+    }
+    #[cfg(windows)]
+    unsafe {
+        use windows::Win32::System::Console::{CTRL_BREAK_EVENT, CTRL_C_EVENT, SetConsoleCtrlHandler};
+        use windows::core::BOOL;
+
+        // Runs on a thread of its own. On close, logoff and shutdown the
+        // process ends as soon as this returns, so wait for the release.
+        unsafe extern "system" fn on_ctrl(kind: u32) -> BOOL {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+            if kind != CTRL_C_EVENT && kind != CTRL_BREAK_EVENT {
+                let deadline = std::time::Instant::now() + Duration::from_secs(4);
+                while !RELEASED.load(Ordering::SeqCst) && std::time::Instant::now() < deadline {
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+            }
+            true.into()
+        }
+        SetConsoleCtrlHandler(Some(on_ctrl), true).ok();
+    }
+}
+
+pub fn attach_console() {
+    #[cfg(windows)]
+    unsafe {
+        use windows::Win32::System::Console::{ATTACH_PARENT_PROCESS, AttachConsole};
+        AttachConsole(ATTACH_PARENT_PROCESS).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_args(args: &[&str]) -> RunArgs {
+        let cli = Cli::try_parse_from([&["autoclick", "run"], args].concat()).unwrap();
+        match cli.command {
+            Some(Command::Run(args)) => args,
+            _ => panic!("not a run command"),
+        }
+    }
+
+    #[test]
+    fn parses_the_documented_example() {
+        let args = run_args(&["--window-title", "Test App", "--interval", "100", "--button", "right", "--count", "500", "--hold", "ctrl"]);
+        assert_eq!(args.window_title.as_deref(), Some("Test App"));
+        assert_eq!(args.interval, 100);
+        assert!(matches!(args.button, Button::Right));
+        assert_eq!(args.count, Some(500));
+        assert!(args.hold == [Modifier::Ctrl]);
+    }
+
+    #[test]
+    fn rejects_a_run_without_window() {
+        assert!(Cli::try_parse_from(["autoclick", "run", "--count", "5"]).is_err());
+        assert!(Cli::try_parse_from(["autoclick", "run", "--window-id", "7", "--interval", "0"]).is_err());
+        for cps in ["1e-300", "0", "1001", "NaN"] {
+            assert!(Cli::try_parse_from(["autoclick", "run", "--window-id", "7", "--cps", cps]).is_err(), "{}", cps);
+        }
+    }
+
+    #[test]
+    fn finds_window_by_exact_title_first() {
+        let window = |hwnd, title: &str, process: &str| WindowInfo {
+            hwnd,
+            title: title.to_string(),
+            process: Some(process.to_string()),
+            ..WindowInfo::default()
+        };
+        let windows = vec![window(1, "Test App - Editor", "edit.exe"), window(2, "Test App", "app.exe")];
+        assert_eq!(find_window(&run_args(&["--window-title", "Test App"]), &windows), Some(2));
+        assert_eq!(find_window(&run_args(&["--window-title", "Editor"]), &windows), Some(1));
+        assert_eq!(find_window(&run_args(&["--window-id", "3"]), &windows), None);
+        assert_eq!(find_window(&run_args(&["--window-title", "Test App", "--process", "EDIT.exe"]), &windows), Some(1));
+        assert_eq!(parse_point("12, 40"), Ok((12, 40)));
+        assert_eq!(parse_key("page down"), Ok(0x22));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn termination_signal_is_caught() {
+        catch_interrupts();
+        unsafe { libc::raise(libc::SIGTERM) };
+        assert!(INTERRUPTED.swap(false, Ordering::SeqCst));
+    }
+}
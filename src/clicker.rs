@@ -1,90 +1,957 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
 use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use std::time::{Duration, Instant};
 use parking_lot::RwLock;
-use windows::Win32::Foundation::{HWND, LPARAM, WPARAM};
-use windows::Win32::UI::WindowsAndMessaging::{PostMessageW, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN, WM_LBUTTONUP};
+use serde::{Deserialize, Serialize};
+
+use crate::backend::{BackendResult, InputBackend, MouseButton, VK_LCONTROL, VK_LSHIFT};
+use crate::macros::{Macro, Step, char_to_key};
+use crate::script::ScriptRunner;
+use crate::scheduler::{Pacer, RateReport};
+use crate::session::{Session, SessionStatus, StopRules};
+use crate::stats::{SessionStats, StatsCollector};
+use crate::timing::{self, IntervalSampler, Timing};
+use crate::window_manager::WindowManager;
+use crate::worker::Worker;
+use tracing::{Span, debug, info, info_span, trace, warn};
+
+/// Where in the target's client area the click lands.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "unit", rename_all = "snake_case")]
+pub enum ClickPoint {
+    Pixels { x: i32, y: i32 },
+    /// Fractions of the client size in percent, so the point follows the
+    /// window when it is resized.
+    Percent { x: f32, y: f32 },
+}
+
+impl Default for ClickPoint {
+    fn default() -> Self {
+        ClickPoint::Pixels { x: 0, y: 0 }
+    }
+}
+
+impl ClickPoint {
+    /// Client coordinates for a window of `client_size`; a percentage point
+    /// falls back to the corner while the size is unknown.
+    pub fn resolve(self, client_size: Option<(i32, i32)>) -> (i32, i32) {
+        match self {
+            ClickPoint::Pixels { x, y } => (x, y),
+            ClickPoint::Percent { x, y } => match client_size {
+                Some((width, height)) => (
+                    (width as f32 * x / 100.0).round() as i32,
+                    (height as f32 * y / 100.0).round() as i32,
+                ),
+                None => (0, 0),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ClickCount {
+    #[default]
+    Single,
+    Double,
+    Triple,
+}
+
+impl ClickCount {
+    pub fn presses(self) -> usize {
+        match self {
+            ClickCount::Single => 1,
+            ClickCount::Double => 2,
+            ClickCount::Triple => 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ClickAction {
+    pub button: MouseButton,
+    pub count: ClickCount,
+    pub point: ClickPoint,
+}
+
+// Client sizes are asked of the window system at most twice a second, not on
+// every click.
+pub struct ClientSizeCache {
+    hwnd: Option<isize>,
+    size: Option<(i32, i32)>,
+    fetched: Instant,
+}
+
+impl ClientSizeCache {
+    const MAX_AGE: Duration = Duration::from_millis(500);
+
+    pub fn new() -> Self {
+        Self { hwnd: None, size: None, fetched: Instant::now() }
+    }
+
+    pub fn get(&mut self, hwnd: isize) -> Option<(i32, i32)> {
+        if self.hwnd != Some(hwnd) || self.fetched.elapsed() > Self::MAX_AGE {
+            self.hwnd = Some(hwnd);
+            self.size = WindowManager::client_size(hwnd);
+            self.fetched = Instant::now();
+        }
+        self.size
+    }
+}
+
+// What a macro run has pressed and not released yet.
+#[derive(Default)]
+struct HeldInput {
+    buttons: Vec<(MouseButton, i32, i32)>,
+    keys: Vec<u32>,
+}
+
+impl HeldInput {
+    fn key_down(&mut self, backend: &mut dyn InputBackend, hwnd: isize, vk: u32) -> u32 {
+        if !self.keys.contains(&vk) {
+            self.keys.push(vk);
+        }
+        refused(hwnd, backend.key_down(hwnd, vk))
+    }
+
+    fn key_up(&mut self, backend: &mut dyn InputBackend, hwnd: isize, vk: u32) -> u32 {
+        self.keys.retain(|held| *held != vk);
+        refused(hwnd, backend.key_up(hwnd, vk))
+    }
+}
+
+// 1 when the backend did not send a post, for the session's failed count.
+fn refused(hwnd: isize, result: BackendResult) -> u32 {
+    match result {
+        Ok(()) => 0,
+        Err(error) => {
+            debug!(hwnd, %error, "post failed");
+            1
+        }
+    }
+}
+
+/// Platform-independent part of the click thread: keeps the held modifiers in
+/// sync with the UI toggles and sends one click (or one macro run) per tick
+/// at client-area positions.
+pub struct ClickLoop {
+    backend: Box<dyn InputBackend>,
+    target: Option<isize>,
+    was_shift_held: bool,
+    was_ctrl_held: bool,
+    /// Keys pressed through `key` (scripts) and not released yet.
+    held_keys: Vec<u32>,
+}
+
+impl ClickLoop {
+    pub fn new(backend: Box<dyn InputBackend>) -> Self {
+        Self {
+            backend,
+            target: None,
+            was_shift_held: false,
+            was_ctrl_held: false,
+            held_keys: Vec::new(),
+        }
+    }
+
+    /// Sends one click; returns how many of its posts the backend refused.
+    pub fn tick(
+        &mut self,
+        hwnd: isize,
+        action: ClickAction,
+        client_size: Option<(i32, i32)>,
+        hold_shift: bool,
+        hold_ctrl: bool,
+    ) -> u32 {
+        self.prepare(hwnd, hold_shift, hold_ctrl);
+
+        // Every second press is the double-click one, so a triple click is
+        // down, up, double-down, up, down, up — the order Windows produces.
+        let (x, y) = action.point.resolve(client_size);
+        let mut failed = 0;
+        for press in 0..action.count.presses() {
+            let down = if press % 2 == 1 {
+                self.backend.mouse_double_down(hwnd, action.button, x, y)
+            } else {
+                self.backend.mouse_down(hwnd, action.button, x, y)
+            };
+            let up = self.backend.mouse_up(hwnd, action.button, x, y);
+            failed += refused(hwnd, down) + refused(hwnd, up);
+        }
+        trace!(hwnd, x, y, button = ?action.button, failed, "click posted");
+        failed
+    }
+
+    /// Plays the macro once. Stops early when `running` goes false; either
+    /// way, buttons and keys the macro left pressed are released. Returns how
+    /// many posts the backend refused.
+    pub fn play(&mut self, hwnd: isize, steps: &[Step], hold_shift: bool, hold_ctrl: bool, running: &AtomicBool) -> u32 {
+        self.prepare(hwnd, hold_shift, hold_ctrl);
+
+        let labels: HashMap<&str, usize> = steps
+            .iter()
+            .enumerate()
+            .filter_map(|(i, step)| match step {
+                Step::Label(name) => Some((name.as_str(), i)),
+                _ => None,
+            })
+            .collect();
+        let mut loop_ends = HashMap::new();
+        let mut open = Vec::new();
+        for (i, step) in steps.iter().enumerate() {
+            match step {
+                Step::Loop(_) => open.push(i),
+                Step::EndLoop => {
+                    if let Some(start) = open.pop() {
+                        loop_ends.insert(start, i);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // (index of the Loop step, runs left)
+        let mut frames: Vec<(usize, u32)> = Vec::new();
+        let mut held = HeldInput::default();
+        let mut failed = 0;
+        let mut pc = 0;
+        while pc < steps.len() && running.load(Ordering::SeqCst) {
+            match &steps[pc] {
+                Step::Loop(count) => frames.push((pc, *count)),
+                Step::EndLoop => {
+                    if let Some((start, left)) = frames.last_mut()
+                        && loop_ends.get(start) == Some(&pc)
+                    {
+                        *left -= 1;
+                        if *left > 0 {
+                            pc = *start + 1;
+                            continue;
+                        }
+                        frames.pop();
+                    }
+                }
+                Step::Label(_) => {}
+                Step::Goto(name) => {
+                    if let Some(&target) = labels.get(name.as_str()) {
+                        // Jumping out of a loop abandons its remaining runs.
+                        frames.retain(|(start, _)| *start < target && loop_ends.get(start).is_some_and(|end| target <= *end));
+                        pc = target;
+                        continue;
+                    }
+                }
+                step => failed += self.perform(hwnd, step, &mut held, running),
+            }
+            pc += 1;
+        }
+
+        for (button, x, y) in held.buttons {
+            failed += refused(hwnd, self.backend.mouse_up(hwnd, button, x, y));
+        }
+        for vk in held.keys.into_iter().rev() {
+            failed += refused(hwnd, self.backend.key_up(hwnd, vk));
+        }
+        failed
+    }
+
+    // Sends one step; returns how many of its posts the backend refused.
+    fn perform(&mut self, hwnd: isize, step: &Step, held: &mut HeldInput, running: &AtomicBool) -> u32 {
+        let backend = &mut *self.backend;
+        match *step {
+            Step::Move { x, y } => refused(hwnd, backend.mouse_move(hwnd, x, y)),
+            Step::Click { button, x, y } => {
+                refused(hwnd, backend.mouse_down(hwnd, button, x, y)) + refused(hwnd, backend.mouse_up(hwnd, button, x, y))
+            }
+            Step::MouseDown { button, x, y } => {
+                held.buttons.push((button, x, y));
+                refused(hwnd, backend.mouse_down(hwnd, button, x, y))
+            }
+            Step::MouseUp { button, x, y } => {
+                held.buttons.retain(|(held, _, _)| *held != button);
+                refused(hwnd, backend.mouse_up(hwnd, button, x, y))
+            }
+            Step::KeyDown(vk) => held.key_down(backend, hwnd, vk),
+            Step::KeyUp(vk) => held.key_up(backend, hwnd, vk),
+            Step::Hold(modifier) => held.key_down(backend, hwnd, modifier.vk()),
+            Step::Release(modifier) => held.key_up(backend, hwnd, modifier.vk()),
+            Step::KeyTap(vk) => refused(hwnd, backend.key_down(hwnd, vk)) + refused(hwnd, backend.key_up(hwnd, vk)),
+            Step::Type(ref text) => {
+                let mut failed = 0;
+                for (vk, shift) in text.chars().filter_map(char_to_key) {
+                    if shift {
+                        failed += refused(hwnd, backend.key_down(hwnd, VK_LSHIFT));
+                    }
+                    failed += refused(hwnd, backend.key_down(hwnd, vk)) + refused(hwnd, backend.key_up(hwnd, vk));
+                    if shift {
+                        failed += refused(hwnd, backend.key_up(hwnd, VK_LSHIFT));
+                    }
+                }
+                failed
+            }
+            Step::Wait(ms) => {
+                Self::wait(Duration::from_millis(ms), running);
+                0
+            }
+            Step::Loop(_) | Step::EndLoop | Step::Label(_) | Step::Goto(_) => 0,
+        }
+    }
+
+    /// Sleeps in short slices so a long pause in a macro or script does not
+    /// delay Stop.
+    pub fn wait(duration: Duration, running: &AtomicBool) {
+        let deadline = Instant::now() + duration;
+        while running.load(Ordering::SeqCst) {
+            let left = deadline.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                break;
+            }
+            std::thread::sleep(left.min(Duration::from_millis(10)));
+        }
+    }
+
+    /// Presses or releases a key for a script; returns 1 when the backend
+    /// refused the post.
+    pub fn key(&mut self, hwnd: isize, vk: u32, down: bool) -> u32 {
+        self.retarget(hwnd);
+        if down {
+            if !self.held_keys.contains(&vk) {
+                self.held_keys.push(vk);
+            }
+            refused(hwnd, self.backend.key_down(hwnd, vk))
+        } else {
+            self.held_keys.retain(|held| *held != vk);
+            refused(hwnd, self.backend.key_up(hwnd, vk))
+        }
+    }
+
+    fn prepare(&mut self, hwnd: isize, hold_shift: bool, hold_ctrl: bool) {
+        self.retarget(hwnd);
+
+        Self::sync_modifier(&mut *self.backend, hwnd, VK_LSHIFT, hold_shift, &mut self.was_shift_held);
+        Self::sync_modifier(&mut *self.backend, hwnd, VK_LCONTROL, hold_ctrl, &mut self.was_ctrl_held);
+    }
+
+    fn retarget(&mut self, hwnd: isize) {
+        // Input held in the previous window must not stay stuck there.
+        if let Some(previous) = self.target.filter(|prev| *prev != hwnd) {
+            info!(from = previous, to = hwnd, "target window changed");
+            self.release_all();
+        }
+        self.target = Some(hwnd);
+    }
+
+    pub fn release_modifiers(&mut self) {
+        if let Some(hwnd) = self.target {
+            Self::sync_modifier(&mut *self.backend, hwnd, VK_LSHIFT, false, &mut self.was_shift_held);
+            Self::sync_modifier(&mut *self.backend, hwnd, VK_LCONTROL, false, &mut self.was_ctrl_held);
+        }
+    }
+
+    /// Lets go of everything still held in the target: keys a script left
+    /// down, then the modifiers.
+    pub fn release_all(&mut self) {
+        if let Some(hwnd) = self.target {
+            for vk in std::mem::take(&mut self.held_keys).into_iter().rev() {
+                self.backend.key_up(hwnd, vk).ok();
+            }
+        }
+        self.release_modifiers();
+    }
+
+    fn sync_modifier(backend: &mut dyn InputBackend, hwnd: isize, vk: u32, wanted: bool, held: &mut bool) {
+        if wanted == *held {
+            return;
+        }
+        if wanted {
+            backend.key_down(hwnd, vk).ok();
+        } else {
+            backend.key_up(hwnd, vk).ok();
+        }
+        *held = wanted;
+    }
+}
+
+/// Values the click thread reads on every iteration; the UI writes them.
+#[derive(Clone, Default)]
+pub struct ClickerControls {
+    pub running: Arc<AtomicBool>,
+    pub selected_hwnd: Arc<RwLock<Option<isize>>>,
+    pub interval_ms: Arc<RwLock<u64>>,
+    /// Clicks-per-second target; overrides `interval_ms` when set.
+    pub cps: Arc<RwLock<Option<f64>>>,
+    /// Distribution the pause after each tick is drawn from.
+    pub timing: Arc<RwLock<Timing>>,
+    pub click: Arc<RwLock<ClickAction>>,
+    /// When set, each tick plays this macro instead of clicking.
+    pub playback: Arc<RwLock<Option<Macro>>>,
+    /// When set, each tick runs this script instead; takes precedence over
+    /// `playback`.
+    pub script: Arc<RwLock<Option<String>>>,
+    /// Why the last script run failed, for the UI.
+    pub script_error: Arc<RwLock<Option<String>>>,
+    pub hold_shift: Arc<AtomicBool>,
+    pub hold_ctrl: Arc<AtomicBool>,
+    /// Measured rate and drift, written by the click thread.
+    pub rate: Arc<RwLock<RateReport>>,
+    /// Limits that clear `running` on their own.
+    pub stop_rules: Arc<RwLock<StopRules>>,
+    /// Extra limits for the current session only, e.g. the duration of a
+    /// scheduled start; cleared when the session ends.
+    pub run_limits: Arc<RwLock<StopRules>>,
+    /// Progress of the current session, or of the last one after it stopped.
+    pub session: Arc<RwLock<SessionStatus>>,
+    /// Counters of the current (or last) session, written by the click thread.
+    pub stats: Arc<RwLock<SessionStats>>,
+}
+
+impl ClickerControls {
+    /// Hands a script to the click thread; an empty script means plain
+    /// clicks even when enabled. A script that does not compile is not
+    /// handed over: the error is shown and the running one carries on.
+    pub fn publish_script(&self, source: &str, enabled: bool) {
+        let script = (enabled && !source.trim().is_empty()).then(|| source.to_string());
+        if let Some(Err(error)) = script.as_deref().map(ScriptRunner::check) {
+            *self.script_error.write() = Some(error);
+            return;
+        }
+        *self.script.write() = script;
+        *self.script_error.write() = None;
+    }
+}
 
 pub struct Clicker;
 
 impl Clicker {
-    pub fn start_clicker(
-        running: Arc<AtomicBool>,
-        selected_hwnd: Arc<RwLock<Option<isize>>>,
-        interval_ms: Arc<RwLock<u64>>,
-        hold_shift: Arc<AtomicBool>,
-        hold_ctrl: Arc<AtomicBool>,
-    ) {
-        std::thread::spawn(move || {
-            let mut was_shift_held = false;
-            let mut was_ctrl_held = false;
-
-            loop {
-                if running.load(Ordering::SeqCst) {
-                    if let Some(hwnd_val) = *selected_hwnd.read() {
-                        let hwnd = HWND(hwnd_val as *mut _);
-
-                        let hold_shift_val = hold_shift.load(Ordering::SeqCst);
-                        let hold_ctrl_val = hold_ctrl.load(Ordering::SeqCst);
-                        println!("KEY: WPARAM(0x{:X}) LPARAM(0x{:X})", WPARAM(0).0, LPARAM(0).0);
-
-                        if hold_shift_val != was_shift_held {
-                            if hold_shift_val {
-                                unsafe {
-                                    PostMessageW(Some(hwnd), WM_KEYDOWN, WPARAM(0x10usize), LPARAM(0x001D0001)).ok();
-                                    PostMessageW(Some(hwnd), WM_KEYDOWN, WPARAM(0xA0usize), LPARAM(0x001D0001)).ok();
-                                    PostMessageW(Some(hwnd), WM_KEYDOWN, WPARAM(0xA1usize), LPARAM(0x001D0001)).ok();
-                                }
-                            } else {
-                                unsafe {
-                                    PostMessageW(Some(hwnd), WM_KEYUP, WPARAM(0x10usize), LPARAM(0xC02A0001)).ok();
-                                }
-                            }
-                            was_shift_held = hold_shift_val;
-                        }
+    /// Runs the click thread until the worker is stopped. Clear `running`
+    /// first so a pause in progress ends at once; held input is released in
+    /// the target before the thread exits.
+    pub fn start_clicker(backend: Box<dyn InputBackend>, controls: ClickerControls) -> Worker {
+        Worker::spawn("clicker", move |shutdown| {
+            let click_loop = Rc::new(RefCell::new(ClickLoop::new(backend)));
+            let mut scripts = ScriptRunner::new(click_loop.clone(), controls.clone());
+            let mut client_sizes = ClientSizeCache::new();
+            let mut sampler = IntervalSampler::new(controls.timing.read().clone());
 
-                        if hold_ctrl_val != was_ctrl_held {
-                            if hold_ctrl_val {
-                                unsafe {
-                                    PostMessageW(Some(hwnd), WM_KEYDOWN, WPARAM(0xA2 as usize), LPARAM(0x001D0001)).ok();
-                                }
-                            } else {
-                                unsafe {
-                                    PostMessageW(Some(hwnd), WM_KEYUP, WPARAM(0xA2 as usize), LPARAM(0xC01D0001)).ok();
-                                }
-                            }
-                            was_ctrl_held = hold_ctrl_val;
-                        }
+            let mut pacer = Pacer::new();
+            let mut session = None;
+            let mut stats = StatsCollector::new();
+            let mut span = Span::none();
+            let mut sessions = 0u64;
 
-                        unsafe {
-                            PostMessageW(Some(hwnd), WM_LBUTTONDOWN, WPARAM(1), LPARAM(0)).ok();
-                            PostMessageW(Some(hwnd), WM_LBUTTONUP, WPARAM(0), LPARAM(0)).ok();
-                        }
+            while !shutdown.load(Ordering::SeqCst) {
+                if controls.running.load(Ordering::SeqCst) {
+                    let current = session.get_or_insert_with(|| {
+                        stats = StatsCollector::new();
+                        *controls.stats.write() = SessionStats::default();
+                        sessions += 1;
+                        span = info_span!("session", id = sessions);
+                        span.in_scope(|| info!(rules = ?controls.stop_rules.read().tightest(*controls.run_limits.read()), "session started"));
+                        Session::start()
+                    });
+                    let _entered = span.enter();
+                    // A changed distribution or seed starts a fresh sequence.
+                    let timing = controls.timing.read().clone();
+                    if timing != *sampler.timing() {
+                        sampler = IntervalSampler::new(timing);
                     }
-                    let ms = *interval_ms.read();
-                    std::thread::sleep(std::time::Duration::from_millis(ms));
-                } else {
-                    if was_shift_held {
-                        if let Some(hwnd_val) = *selected_hwnd.read() {
-                            let hwnd = HWND(hwnd_val as *mut _);
-                            unsafe {
-                                PostMessageW(Some(hwnd), WM_KEYUP, WPARAM(0x10 as usize), LPARAM(0xC02A0001)).ok();
-                            }
-                        }
-                        was_shift_held = false;
+                    let base = timing::base_period(*controls.interval_ms.read(), *controls.cps.read());
+                    let pause = sampler.next(base);
+                    if !pacer.wait(pause, &controls.running) || Self::stop_if_done(&controls, current) {
+                        continue;
                     }
 
-                    if was_ctrl_held {
-                        if let Some(hwnd_val) = *selected_hwnd.read() {
-                            let hwnd = HWND(hwnd_val as *mut _);
-                            unsafe {
-                                PostMessageW(Some(hwnd), WM_KEYUP, WPARAM(0xA2 as usize), LPARAM(0xC01D0001)).ok();
+                    if let Some(hwnd) = *controls.selected_hwnd.read() {
+                        let hold_shift = controls.hold_shift.load(Ordering::SeqCst);
+                        let hold_ctrl = controls.hold_ctrl.load(Ordering::SeqCst);
+                        let script = controls.script.read().clone();
+                        let playback = controls.playback.read().clone();
+                        let failed = match (script, playback) {
+                            (Some(source), _) => {
+                                // A broken script is not retried every tick. The
+                                // error stays until another script is published.
+                                if let Err(error) = scripts.run(&source) {
+                                    warn!(%error, "script failed, stopping");
+                                    controls.running.store(false, Ordering::SeqCst);
+                                    *controls.script_error.write() = Some(error);
+                                }
+                                pacer.rebase();
+                                stats.interrupt();
+                                scripts.take_failed()
+                            }
+                            (None, Some(recorded)) => {
+                                let failed = click_loop.borrow_mut().play(
+                                    hwnd,
+                                    &recorded.steps,
+                                    hold_shift,
+                                    hold_ctrl,
+                                    &controls.running,
+                                );
+                                pacer.rebase();
+                                stats.interrupt();
+                                failed
                             }
+                            (None, None) => {
+                                let action = *controls.click.read();
+                                let client_size = match action.point {
+                                    ClickPoint::Percent { .. } => client_sizes.get(hwnd),
+                                    ClickPoint::Pixels { .. } => None,
+                                };
+                                click_loop.borrow_mut().tick(hwnd, action, client_size, hold_shift, hold_ctrl)
+                            }
+                        };
+                        current.clicks += 1;
+                        stats.tick(pause, failed);
+                        *controls.stats.write() = stats.snapshot();
+                    }
+                    *controls.rate.write() = pacer.report();
+                    Self::stop_if_done(&controls, current);
+                } else {
+                    // Same path for a manual stop and a fired stop rule.
+                    if let Some(ended) = session.take() {
+                        let status = *controls.session.read();
+                        let failed = controls.stats.read().failed;
+                        span.in_scope(|| info!(clicks = ended.clicks, failed, reason = ?status.stopped, "session ended"));
+                        if failed > 0 {
+                            span.in_scope(|| warn!(failed, "some posts did not go out"));
                         }
-                        was_ctrl_held = false;
+                        span = Span::none();
+                        *controls.run_limits.write() = StopRules::default();
                     }
-
-                    std::thread::sleep(std::time::Duration::from_millis(60));
+                    click_loop.borrow_mut().release_all();
+                    pacer.reset();
+                    *controls.rate.write() = RateReport::default();
+                    std::thread::sleep(Duration::from_millis(60));
                 }
             }
-        });
+            click_loop.borrow_mut().release_all();
+        })
+    }
+
+    // Publishes the session progress and clears `running` once a stop rule
+    // fires; the idle branch then releases held input as for a manual stop.
+    fn stop_if_done(controls: &ClickerControls, session: &Session) -> bool {
+        let rules = controls.stop_rules.read().tightest(*controls.run_limits.read());
+        let status = session.status(&rules);
+        *controls.session.write() = status;
+        if let Some(reason) = status.stopped {
+            info!(?reason, clicks = status.clicks, "stop rule fired");
+            controls.running.store(false, Ordering::SeqCst);
+        }
+        status.stopped.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::recording::{InputEvent, RecordingBackend};
+
+    const HWND: isize = 0x1234;
+
+    fn click(target: isize) -> [InputEvent; 2] {
+        [
+            InputEvent::MouseDown { target, button: MouseButton::Left, x: 0, y: 0 },
+            InputEvent::MouseUp { target, button: MouseButton::Left, x: 0, y: 0 },
+        ]
+    }
+
+    fn recording_loop() -> (ClickLoop, RecordingBackend) {
+        let recorder = RecordingBackend::new();
+        (ClickLoop::new(Box::new(recorder.clone())), recorder)
+    }
+
+    #[test]
+    fn plain_clicks_without_modifiers() {
+        let (mut click_loop, recorder) = recording_loop();
+        click_loop.tick(HWND, ClickAction::default(), None, false, false);
+        click_loop.tick(HWND, ClickAction::default(), None, false, false);
+        click_loop.release_modifiers();
+
+        let expected: Vec<_> = [click(HWND), click(HWND)].concat();
+        assert_eq!(recorder.trace(), expected);
+    }
+
+    #[test]
+    fn shift_is_pressed_once_and_released_on_stop() {
+        let (mut click_loop, recorder) = recording_loop();
+        for _ in 0..3 {
+            click_loop.tick(HWND, ClickAction::default(), None, true, false);
+        }
+        click_loop.release_modifiers();
+
+        let mut expected = vec![InputEvent::KeyDown { target: HWND, vk: VK_LSHIFT }];
+        for _ in 0..3 {
+            expected.extend(click(HWND));
+        }
+        expected.push(InputEvent::KeyUp { target: HWND, vk: VK_LSHIFT });
+        assert_eq!(recorder.trace(), expected);
+    }
+
+    #[test]
+    fn clicks_use_requested_button() {
+        let (mut click_loop, recorder) = recording_loop();
+        click_loop.tick(HWND, ClickAction { button: MouseButton::Right, ..ClickAction::default() }, None, false, false);
+
+        assert_eq!(
+            recorder.trace(),
+            vec![
+                InputEvent::MouseDown { target: HWND, button: MouseButton::Right, x: 0, y: 0 },
+                InputEvent::MouseUp { target: HWND, button: MouseButton::Right, x: 0, y: 0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn clicks_land_at_requested_position() {
+        let (mut click_loop, recorder) = recording_loop();
+        let action = ClickAction { point: ClickPoint::Pixels { x: 120, y: 45 }, ..ClickAction::default() };
+        click_loop.tick(HWND, action, None, false, false);
+
+        assert_eq!(
+            recorder.trace(),
+            vec![
+                InputEvent::MouseDown { target: HWND, button: MouseButton::Left, x: 120, y: 45 },
+                InputEvent::MouseUp { target: HWND, button: MouseButton::Left, x: 120, y: 45 },
+            ]
+        );
+    }
+
+    #[test]
+    fn triple_click_sends_double_click_press_second() {
+        let (mut click_loop, recorder) = recording_loop();
+        let action = ClickAction {
+            button: MouseButton::X1,
+            count: ClickCount::Triple,
+            point: ClickPoint::Percent { x: 10.0, y: 50.0 },
+        };
+        click_loop.tick(HWND, action, Some((200, 100)), false, false);
+
+        let (target, button, x, y) = (HWND, MouseButton::X1, 20, 50);
+        assert_eq!(
+            recorder.trace(),
+            vec![
+                InputEvent::MouseDown { target, button, x, y },
+                InputEvent::MouseUp { target, button, x, y },
+                InputEvent::MouseDoubleDown { target, button, x, y },
+                InputEvent::MouseUp { target, button, x, y },
+                InputEvent::MouseDown { target, button, x, y },
+                InputEvent::MouseUp { target, button, x, y },
+            ]
+        );
+    }
+
+    #[test]
+    fn refused_posts_are_counted() {
+        use crate::backend::{BackendError, UnavailableBackend};
+
+        let backend = UnavailableBackend(BackendError("gone".to_string()));
+        let mut click_loop = ClickLoop::new(Box::new(backend));
+        assert_eq!(click_loop.tick(HWND, ClickAction::default(), None, false, false), 2);
+
+        // Move 1, click 2, tap 2, "A" with shift 4, then the held key is
+        // released at the end: 1 for the press and 1 for the release.
+        let steps = vec![
+            Step::Move { x: 1, y: 2 },
+            Step::Click { button: MouseButton::Left, x: 1, y: 2 },
+            Step::KeyTap(0x41),
+            Step::Type("A".to_string()),
+            Step::KeyDown(0x42),
+        ];
+        assert_eq!(click_loop.play(HWND, &steps, false, false, &AtomicBool::new(true)), 11);
+    }
+
+    #[test]
+    fn macro_replays_steps_in_order() {
+        let (mut click_loop, recorder) = recording_loop();
+        let steps = [
+            Step::KeyDown(0x41),
+            Step::Move { x: 30, y: 20 },
+            Step::MouseDown { button: MouseButton::Right, x: 30, y: 20 },
+            Step::Wait(20),
+            Step::MouseUp { button: MouseButton::Right, x: 30, y: 20 },
+            Step::KeyUp(0x41),
+        ];
+        click_loop.play(HWND, &steps, false, false, &AtomicBool::new(true));
+
+        let events = recorder.events();
+        assert!(events[3].at - events[2].at >= Duration::from_millis(20));
+        assert_eq!(
+            recorder.trace(),
+            vec![
+                InputEvent::KeyDown { target: HWND, vk: 0x41 },
+                InputEvent::MouseMove { target: HWND, x: 30, y: 20 },
+                InputEvent::MouseDown { target: HWND, button: MouseButton::Right, x: 30, y: 20 },
+                InputEvent::MouseUp { target: HWND, button: MouseButton::Right, x: 30, y: 20 },
+                InputEvent::KeyUp { target: HWND, vk: 0x41 },
+            ]
+        );
+    }
+
+    #[test]
+    fn loops_labels_and_typing() {
+        let (mut click_loop, recorder) = recording_loop();
+        let parsed = Macro::parse(
+            "loop 2\n  loop 2\n    tap a\n  end\n  goto out\n  tap b\nend\nlabel out\ntype \"B!\"\n",
+        )
+        .unwrap();
+        click_loop.play(HWND, &parsed.steps, false, false, &AtomicBool::new(true));
+
+        let keys: Vec<(bool, u32)> = recorder
+            .trace()
+            .into_iter()
+            .map(|event| match event {
+                InputEvent::KeyDown { vk, .. } => (true, vk),
+                InputEvent::KeyUp { vk, .. } => (false, vk),
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        let tap = |vk| [(true, vk), (false, vk)];
+        let shifted = |vk| [(true, VK_LSHIFT), (true, vk), (false, vk), (false, VK_LSHIFT)];
+        assert_eq!(keys, [tap(0x41), tap(0x41)].concat().into_iter().chain(shifted(0x42)).chain(shifted(0x31)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn stopped_macro_releases_what_it_holds() {
+        let (mut click_loop, recorder) = recording_loop();
+        let running = Arc::new(AtomicBool::new(true));
+        let steps = [
+            Step::KeyDown(0x41),
+            Step::MouseDown { button: MouseButton::Left, x: 5, y: 5 },
+            Step::Wait(10_000),
+            Step::MouseUp { button: MouseButton::Left, x: 5, y: 5 },
+            Step::KeyUp(0x41),
+        ];
+
+        let stopper = {
+            let running = running.clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(50));
+                running.store(false, Ordering::SeqCst);
+            })
+        };
+        let started = Instant::now();
+        click_loop.play(HWND, &steps, false, false, &running);
+        stopper.join().unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(
+            recorder.trace(),
+            vec![
+                InputEvent::KeyDown { target: HWND, vk: 0x41 },
+                InputEvent::MouseDown { target: HWND, button: MouseButton::Left, x: 5, y: 5 },
+                InputEvent::MouseUp { target: HWND, button: MouseButton::Left, x: 5, y: 5 },
+                InputEvent::KeyUp { target: HWND, vk: 0x41 },
+            ]
+        );
+    }
+
+    #[test]
+    fn percent_point_scales_with_client_size() {
+        let point = ClickPoint::Percent { x: 50.0, y: 25.0 };
+        assert_eq!(point.resolve(Some((800, 600))), (400, 150));
+        assert_eq!(point.resolve(Some((333, 101))), (167, 25));
+        assert_eq!(point.resolve(None), (0, 0));
+        assert_eq!(ClickPoint::Pixels { x: 7, y: 9 }.resolve(Some((800, 600))), (7, 9));
+    }
+
+    #[test]
+    fn ctrl_toggled_mid_session() {
+        let (mut click_loop, recorder) = recording_loop();
+        click_loop.tick(HWND, ClickAction::default(), None, false, false);
+        click_loop.tick(HWND, ClickAction::default(), None, false, true);
+        click_loop.tick(HWND, ClickAction::default(), None, false, true);
+        click_loop.tick(HWND, ClickAction::default(), None, false, false);
+
+        let expected: Vec<_> = [
+            click(HWND).to_vec(),
+            vec![InputEvent::KeyDown { target: HWND, vk: VK_LCONTROL }],
+            click(HWND).to_vec(),
+            click(HWND).to_vec(),
+            vec![InputEvent::KeyUp { target: HWND, vk: VK_LCONTROL }],
+            click(HWND).to_vec(),
+        ]
+        .concat();
+        assert_eq!(recorder.trace(), expected);
+    }
+
+    #[test]
+    fn release_is_idempotent() {
+        let (mut click_loop, recorder) = recording_loop();
+        click_loop.tick(HWND, ClickAction::default(), None, true, true);
+        click_loop.release_modifiers();
+        click_loop.release_modifiers();
+
+        let expected: Vec<_> = [
+            vec![
+                InputEvent::KeyDown { target: HWND, vk: VK_LSHIFT },
+                InputEvent::KeyDown { target: HWND, vk: VK_LCONTROL },
+            ],
+            click(HWND).to_vec(),
+            vec![
+                InputEvent::KeyUp { target: HWND, vk: VK_LSHIFT },
+                InputEvent::KeyUp { target: HWND, vk: VK_LCONTROL },
+            ],
+        ]
+        .concat();
+        assert_eq!(recorder.trace(), expected);
+    }
+
+    #[test]
+    fn switching_target_releases_modifiers_in_previous_window() {
+        let other = 0x5678;
+        let (mut click_loop, recorder) = recording_loop();
+        click_loop.tick(HWND, ClickAction::default(), None, true, false);
+        click_loop.tick(other, ClickAction::default(), None, true, false);
+
+        let expected: Vec<_> = [
+            vec![InputEvent::KeyDown { target: HWND, vk: VK_LSHIFT }],
+            click(HWND).to_vec(),
+            vec![
+                InputEvent::KeyUp { target: HWND, vk: VK_LSHIFT },
+                InputEvent::KeyDown { target: other, vk: VK_LSHIFT },
+            ],
+            click(other).to_vec(),
+        ]
+        .concat();
+        assert_eq!(recorder.trace(), expected);
+    }
+
+    #[test]
+    fn clicker_thread_releases_shift_when_running_flips_off() {
+        let recorder = RecordingBackend::new();
+        let running = Arc::new(AtomicBool::new(false));
+
+        let _worker = Clicker::start_clicker(
+            Box::new(recorder.clone()),
+            ClickerControls {
+                running: running.clone(),
+                selected_hwnd: Arc::new(RwLock::new(Some(HWND))),
+                interval_ms: Arc::new(RwLock::new(5)),
+                hold_shift: Arc::new(AtomicBool::new(true)),
+                ..ClickerControls::default()
+            },
+        );
+
+        running.store(true, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(100));
+        running.store(false, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(150));
+
+        let events = recorder.events();
+        assert!(events.windows(2).all(|w| w[0].at <= w[1].at));
+
+        let trace = recorder.trace();
+        let (first, rest) = trace.split_first().unwrap();
+        let (last, clicks) = rest.split_last().unwrap();
+        assert_eq!(*first, InputEvent::KeyDown { target: HWND, vk: VK_LSHIFT });
+        assert_eq!(*last, InputEvent::KeyUp { target: HWND, vk: VK_LSHIFT });
+        assert!(clicks.len() >= 2 && clicks.len() % 2 == 0);
+        assert!(clicks.chunks(2).all(|pair| pair == click(HWND)));
+    }
+
+    #[test]
+    fn click_limit_stops_and_releases_shift() {
+        let recorder = RecordingBackend::new();
+        let controls = ClickerControls {
+            selected_hwnd: Arc::new(RwLock::new(Some(HWND))),
+            interval_ms: Arc::new(RwLock::new(2)),
+            hold_shift: Arc::new(AtomicBool::new(true)),
+            stop_rules: Arc::new(RwLock::new(StopRules { max_clicks: Some(3), ..StopRules::default() })),
+            ..ClickerControls::default()
+        };
+        let _worker = Clicker::start_clicker(Box::new(recorder.clone()), controls.clone());
+
+        controls.running.store(true, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(200));
+
+        assert!(!controls.running.load(Ordering::SeqCst));
+        let status = *controls.session.read();
+        assert_eq!(status.clicks, 3);
+        assert_eq!(status.stopped, Some(crate::session::StopReason::ClickLimit));
+
+        let expected: Vec<_> = [
+            vec![InputEvent::KeyDown { target: HWND, vk: VK_LSHIFT }],
+            click(HWND).to_vec(),
+            click(HWND).to_vec(),
+            click(HWND).to_vec(),
+            vec![InputEvent::KeyUp { target: HWND, vk: VK_LSHIFT }],
+        ]
+        .concat();
+        assert_eq!(recorder.trace(), expected);
+    }
+
+    #[test]
+    fn script_that_does_not_compile_leaves_running_one_alone() {
+        let recorder = RecordingBackend::new();
+        let controls = ClickerControls {
+            selected_hwnd: Arc::new(RwLock::new(Some(HWND))),
+            interval_ms: Arc::new(RwLock::new(2)),
+            ..ClickerControls::default()
+        };
+        controls.publish_script("click();", true);
+        let _worker = Clicker::start_clicker(Box::new(recorder.clone()), controls.clone());
+        controls.running.store(true, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(50));
+
+        // Half typed, as it would be mid-edit.
+        controls.publish_script("click(", true);
+        std::thread::sleep(Duration::from_millis(50));
+        let clicks_before = recorder.trace().len();
+        std::thread::sleep(Duration::from_millis(50));
+
+        assert!(controls.running.load(Ordering::SeqCst));
+        assert_eq!(controls.script.read().as_deref(), Some("click();"));
+        assert!(controls.script_error.read().is_some());
+        assert!(recorder.trace().len() > clicks_before);
+
+        controls.publish_script("click(); click();", true);
+        assert_eq!(controls.script_error.read().as_deref(), None);
+    }
+
+    #[test]
+    fn release_all_lets_go_of_script_keys() {
+        let (mut click_loop, recorder) = recording_loop();
+        click_loop.tick(HWND, ClickAction::default(), None, false, true);
+        click_loop.key(HWND, 0x41, true);
+        click_loop.key(HWND, 0x42, true);
+        click_loop.key(HWND, 0x42, false);
+        click_loop.release_all();
+        click_loop.release_all();
+
+        let expected: Vec<_> = [
+            vec![InputEvent::KeyDown { target: HWND, vk: VK_LCONTROL }],
+            click(HWND).to_vec(),
+            vec![
+                InputEvent::KeyDown { target: HWND, vk: 0x41 },
+                InputEvent::KeyDown { target: HWND, vk: 0x42 },
+                InputEvent::KeyUp { target: HWND, vk: 0x42 },
+                InputEvent::KeyUp { target: HWND, vk: 0x41 },
+                InputEvent::KeyUp { target: HWND, vk: VK_LCONTROL },
+            ],
+        ]
+        .concat();
+        assert_eq!(recorder.trace(), expected);
+    }
+
+    #[test]
+    fn stopping_worker_mid_session_releases_modifiers() {
+        let recorder = RecordingBackend::new();
+        let controls = ClickerControls {
+            selected_hwnd: Arc::new(RwLock::new(Some(HWND))),
+            interval_ms: Arc::new(RwLock::new(5)),
+            hold_shift: Arc::new(AtomicBool::new(true)),
+            ..ClickerControls::default()
+        };
+        let mut worker = Clicker::start_clicker(Box::new(recorder.clone()), controls.clone());
+
+        controls.running.store(true, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(50));
+        controls.running.store(false, Ordering::SeqCst);
+        worker.stop();
+        let stopped_at = recorder.trace().len();
+
+        assert_eq!(recorder.trace().last(), Some(&InputEvent::KeyUp { target: HWND, vk: VK_LSHIFT }));
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(recorder.trace().len(), stopped_at);
     }
-}
\ No newline at end of file
+}
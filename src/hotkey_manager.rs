@@ -1,23 +1,68 @@
-use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
-use parking_lot::RwLock;
-use windows::Win32::UI::Input::KeyboardAndMouse::GetAsyncKeyState;
+use std::collections::HashSet;
+use std::sync::{Arc, OnceLock, atomic::{AtomicBool, Ordering}, mpsc::{self, Receiver, RecvTimeoutError, Sender}};
+use std::time::Duration;
+use parking_lot::{Mutex, RwLock};
+use rdev::{Button, EventType, Key};
 
-pub struct HotkeyManager;
+use crate::backend::MouseButton;
+use crate::worker::Worker;
+use tracing::{debug, error};
 
-impl HotkeyManager {
-    pub fn vk_for_label(label: &str) -> u32 {
-        match label {
-            "F6" => 0x75,
-            "F7" => 0x76,
-            "F8" => 0x77,
-            "F9" => 0x78,
-            "F10" => 0x79,
-            "R" => 0x52,
-            "PgDn" => 0x22,
-            _ => 0x75,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEdge {
+    Pressed(u32),
+    Released(u32),
+    /// Mouse buttons, with the screen position of the pointer at the time.
+    ButtonPressed { button: MouseButton, x: i32, y: i32 },
+    ButtonReleased { button: MouseButton, x: i32, y: i32 },
+}
+
+// Turns rdev's key events into edges: auto-repeat presses are swallowed, so
+// every physical press yields exactly one Pressed and one Released. Button
+// events carry no position, so the last pointer motion is remembered for them.
+#[derive(Default)]
+struct EdgeFilter {
+    down: HashSet<u32>,
+    pointer: Option<(f64, f64)>,
+}
+
+impl EdgeFilter {
+    fn edge(&mut self, event_type: &EventType) -> Option<KeyEdge> {
+        match *event_type {
+            EventType::KeyPress(key) => {
+                let vk = HotkeyManager::key_to_vk(key)?;
+                self.down.insert(vk).then_some(KeyEdge::Pressed(vk))
+            }
+            EventType::KeyRelease(key) => {
+                let vk = HotkeyManager::key_to_vk(key)?;
+                self.down.remove(&vk).then_some(KeyEdge::Released(vk))
+            }
+            EventType::MouseMove { x, y } => {
+                self.pointer = Some((x, y));
+                None
+            }
+            EventType::ButtonPress(button) => {
+                let (button, (x, y)) = (HotkeyManager::button_from_rdev(button)?, self.position()?);
+                Some(KeyEdge::ButtonPressed { button, x, y })
+            }
+            EventType::ButtonRelease(button) => {
+                let (button, (x, y)) = (HotkeyManager::button_from_rdev(button)?, self.position()?);
+                Some(KeyEdge::ButtonReleased { button, x, y })
+            }
+            _ => None,
         }
     }
 
+    fn position(&self) -> Option<(i32, i32)> {
+        self.pointer.map(|(x, y)| (x.round() as i32, y.round() as i32))
+    }
+}
+
+static SUBSCRIBERS: OnceLock<Mutex<Vec<Sender<KeyEdge>>>> = OnceLock::new();
+
+pub struct HotkeyManager;
+
+impl HotkeyManager {
     pub fn vk_to_key_name(vk: u32) -> String {
         match vk {
             0x08 => "Backspace".to_string(),
@@ -116,20 +161,229 @@ impl HotkeyManager {
         }
     }
 
-    pub fn start_hotkey_listener(hotkey_vk: Arc<RwLock<u32>>, running: Arc<AtomicBool>) {
-        std::thread::spawn(move || {
-            loop {
-                let vk = *hotkey_vk.read();
-                if vk != 0 {
-                    let state = unsafe { GetAsyncKeyState(vk as i32) };
-                    if (state & 0x1) != 0 {
+    /// Global key and mouse button edges from the shared `rdev` listener. The listener thread
+    /// is started on the first call; every receiver sees every edge.
+    pub fn subscribe() -> Receiver<KeyEdge> {
+        let subscribers = SUBSCRIBERS.get_or_init(|| {
+            std::thread::spawn(|| {
+                let mut filter = EdgeFilter::default();
+                rdev::listen(move |event| {
+                    if let Some(edge) = filter.edge(&event.event_type)
+                        && let Some(subscribers) = SUBSCRIBERS.get()
+                    {
+                        subscribers.lock().retain(|tx| tx.send(edge).is_ok());
+                    }
+                })
+                .unwrap_or_else(|error| error!(?error, "global key listener failed"));
+            });
+            Mutex::new(Vec::new())
+        });
+
+        let (tx, rx) = mpsc::channel();
+        subscribers.lock().push(tx);
+        rx
+    }
+
+    /// Toggles `running` whenever `hotkey_vk` is pressed, until the worker
+    /// is stopped.
+    pub fn start_hotkey_listener(hotkey_vk: Arc<RwLock<u32>>, running: Arc<AtomicBool>) -> Worker {
+        let edges = Self::subscribe();
+        Worker::spawn("hotkey", move |shutdown| {
+            while !shutdown.load(Ordering::SeqCst) {
+                match edges.recv_timeout(Duration::from_millis(100)) {
+                    Ok(KeyEdge::Pressed(vk)) if vk != 0 && vk == *hotkey_vk.read() => {
                         let new = !running.load(Ordering::SeqCst);
+                        debug!(key = %Self::vk_to_key_name(vk), running = new, "hotkey pressed");
                         running.store(new, Ordering::SeqCst);
-                        std::thread::sleep(std::time::Duration::from_millis(250));
                     }
+                    Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => break,
                 }
-                std::thread::sleep(std::time::Duration::from_millis(30));
             }
-        });
+        })
+    }
+
+    // rdev reports the side buttons as Unknown: the XBUTTON number on
+    // Windows, the core button number on X11.
+    fn button_from_rdev(button: Button) -> Option<MouseButton> {
+        let button = match button {
+            Button::Left => MouseButton::Left,
+            Button::Right => MouseButton::Right,
+            Button::Middle => MouseButton::Middle,
+            #[cfg(windows)]
+            Button::Unknown(1) => MouseButton::X1,
+            #[cfg(windows)]
+            Button::Unknown(2) => MouseButton::X2,
+            #[cfg(not(windows))]
+            Button::Unknown(8) => MouseButton::X1,
+            #[cfg(not(windows))]
+            Button::Unknown(9) => MouseButton::X2,
+            _ => return None,
+        };
+        Some(button)
+    }
+
+    pub fn key_to_vk(key: Key) -> Option<u32> {
+        let vk = match key {
+            Key::Alt => 0xA4,
+            Key::AltGr => 0xA5,
+            Key::Backspace => 0x08,
+            Key::CapsLock => 0x14,
+            Key::ControlLeft => 0xA2,
+            Key::ControlRight => 0xA3,
+            Key::Delete => 0x2E,
+            Key::DownArrow => 0x28,
+            Key::End => 0x23,
+            Key::Escape => 0x1B,
+            Key::F1 => 0x70,
+            Key::F2 => 0x71,
+            Key::F3 => 0x72,
+            Key::F4 => 0x73,
+            Key::F5 => 0x74,
+            Key::F6 => 0x75,
+            Key::F7 => 0x76,
+            Key::F8 => 0x77,
+            Key::F9 => 0x78,
+            Key::F10 => 0x79,
+            Key::F11 => 0x7A,
+            Key::F12 => 0x7B,
+            Key::Home => 0x24,
+            Key::LeftArrow => 0x25,
+            Key::MetaLeft => 0x5B,
+            Key::MetaRight => 0x5C,
+            Key::PageDown => 0x22,
+            Key::PageUp => 0x21,
+            Key::Return | Key::KpReturn => 0x0D,
+            Key::RightArrow => 0x27,
+            Key::ShiftLeft => 0xA0,
+            Key::ShiftRight => 0xA1,
+            Key::Space => 0x20,
+            Key::Tab => 0x09,
+            Key::UpArrow => 0x26,
+            Key::PrintScreen => 0x2C,
+            Key::ScrollLock => 0x91,
+            Key::Pause => 0x13,
+            Key::NumLock => 0x90,
+            Key::BackQuote => 0xC0,
+            Key::Num0 => 0x30,
+            Key::Num1 => 0x31,
+            Key::Num2 => 0x32,
+            Key::Num3 => 0x33,
+            Key::Num4 => 0x34,
+            Key::Num5 => 0x35,
+            Key::Num6 => 0x36,
+            Key::Num7 => 0x37,
+            Key::Num8 => 0x38,
+            Key::Num9 => 0x39,
+            Key::Minus => 0xBD,
+            Key::Equal => 0xBB,
+            Key::KeyA => 0x41,
+            Key::KeyB => 0x42,
+            Key::KeyC => 0x43,
+            Key::KeyD => 0x44,
+            Key::KeyE => 0x45,
+            Key::KeyF => 0x46,
+            Key::KeyG => 0x47,
+            Key::KeyH => 0x48,
+            Key::KeyI => 0x49,
+            Key::KeyJ => 0x4A,
+            Key::KeyK => 0x4B,
+            Key::KeyL => 0x4C,
+            Key::KeyM => 0x4D,
+            Key::KeyN => 0x4E,
+            Key::KeyO => 0x4F,
+            Key::KeyP => 0x50,
+            Key::KeyQ => 0x51,
+            Key::KeyR => 0x52,
+            Key::KeyS => 0x53,
+            Key::KeyT => 0x54,
+            Key::KeyU => 0x55,
+            Key::KeyV => 0x56,
+            Key::KeyW => 0x57,
+            Key::KeyX => 0x58,
+            Key::KeyY => 0x59,
+            Key::KeyZ => 0x5A,
+            Key::LeftBracket => 0xDB,
+            Key::RightBracket => 0xDD,
+            Key::SemiColon => 0xBA,
+            Key::Quote => 0xDE,
+            Key::BackSlash => 0xDC,
+            Key::IntlBackslash => 0xE2,
+            Key::Comma => 0xBC,
+            Key::Dot => 0xBE,
+            Key::Slash => 0xBF,
+            Key::Insert => 0x2D,
+            Key::KpMinus => 0x6D,
+            Key::KpPlus => 0x6B,
+            Key::KpMultiply => 0x6A,
+            Key::KpDivide => 0x6F,
+            Key::Kp0 => 0x60,
+            Key::Kp1 => 0x61,
+            Key::Kp2 => 0x62,
+            Key::Kp3 => 0x63,
+            Key::Kp4 => 0x64,
+            Key::Kp5 => 0x65,
+            Key::Kp6 => 0x66,
+            Key::Kp7 => 0x67,
+            Key::Kp8 => 0x68,
+            Key::Kp9 => 0x69,
+            Key::KpDelete => 0x6E,
+            // rdev passes the raw virtual-key code through on Windows; on X11
+            // it is a keycode and cannot be mapped without the keyboard layout.
+            #[cfg(windows)]
+            Key::Unknown(vk) => vk,
+            _ => return None,
+        };
+        Some(vk)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn autorepeat_yields_a_single_press_edge() {
+        let mut filter = EdgeFilter::default();
+        let press = EventType::KeyPress(Key::F6);
+        let release = EventType::KeyRelease(Key::F6);
+
+        assert_eq!(filter.edge(&press), Some(KeyEdge::Pressed(0x75)));
+        assert_eq!(filter.edge(&press), None);
+        assert_eq!(filter.edge(&press), None);
+        assert_eq!(filter.edge(&release), Some(KeyEdge::Released(0x75)));
+        assert_eq!(filter.edge(&press), Some(KeyEdge::Pressed(0x75)));
+    }
+
+    #[test]
+    fn stray_release_and_mouse_events_are_ignored() {
+        let mut filter = EdgeFilter::default();
+        assert_eq!(filter.edge(&EventType::KeyRelease(Key::KeyR)), None);
+        assert_eq!(filter.edge(&EventType::ButtonPress(Button::Left)), None);
+        assert_eq!(filter.edge(&EventType::MouseMove { x: 1.0, y: 2.0 }), None);
+    }
+
+    #[test]
+    fn buttons_report_last_pointer_position() {
+        let mut filter = EdgeFilter::default();
+        assert_eq!(filter.edge(&EventType::ButtonPress(Button::Left)), None);
+        assert_eq!(filter.edge(&EventType::MouseMove { x: 310.4, y: 95.6 }), None);
+        assert_eq!(
+            filter.edge(&EventType::ButtonPress(Button::Right)),
+            Some(KeyEdge::ButtonPressed { button: MouseButton::Right, x: 310, y: 96 })
+        );
+        assert_eq!(
+            filter.edge(&EventType::ButtonRelease(Button::Right)),
+            Some(KeyEdge::ButtonReleased { button: MouseButton::Right, x: 310, y: 96 })
+        );
+        assert_eq!(filter.edge(&EventType::ButtonPress(Button::Unknown(42))), None);
+    }
+
+    #[test]
+    fn preset_hotkeys_round_trip_through_key_names() {
+        for (key, name) in [(Key::F6, "F6"), (Key::F10, "F10"), (Key::KeyR, "R"), (Key::PageDown, "Page Down")] {
+            assert_eq!(HotkeyManager::key_to_vk(key).map(HotkeyManager::vk_to_key_name).as_deref(), Some(name));
+        }
+        assert_eq!(HotkeyManager::vk_to_key_name(HotkeyManager::key_to_vk(Key::ShiftLeft).unwrap()), "Left Shift");
     }
-}
\ No newline at end of file
+}
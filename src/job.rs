@@ -0,0 +1,154 @@
+use std::sync::{Arc, atomic::Ordering};
+use parking_lot::RwLock;
+
+use crate::backend::{self, InputBackend, UnavailableBackend};
+use crate::clicker::{ClickAction, Clicker, ClickerControls};
+use crate::hotkey_manager::HotkeyManager;
+use crate::settings::{Profile, WindowTarget};
+use crate::window_manager::WindowInfo;
+use crate::worker::Worker;
+use tracing::{info, warn};
+
+/// One clicker with its own thread, target, settings and hotkey. The app
+/// keeps one per profile, so profiles click side by side.
+pub struct Job {
+    pub controls: ClickerControls,
+    pub hotkey_vk: Arc<RwLock<u32>>,
+    /// Script as edited; the click thread only sees it once published.
+    pub script_source: String,
+    pub script_enabled: bool,
+    /// Why no input backend could be opened for this job, if so.
+    pub backend_error: Option<String>,
+    clicker: Worker,
+    hotkey: Worker,
+}
+
+impl Job {
+    pub fn spawn(profile: &Profile, windows: &[WindowInfo]) -> Self {
+        let controls = ClickerControls::default();
+        let hotkey_vk = Arc::new(RwLock::new(0));
+        let (backend_error, clicker, hotkey) = Self::start_workers(&controls, &hotkey_vk);
+        let mut job = Self {
+            controls,
+            hotkey_vk,
+            script_source: String::new(),
+            script_enabled: false,
+            backend_error,
+            clicker,
+            hotkey,
+        };
+        job.load(profile, windows);
+        job
+    }
+
+    // Opens a fresh input backend and starts the click and hotkey threads on
+    // the shared controls.
+    fn start_workers(controls: &ClickerControls, hotkey_vk: &Arc<RwLock<u32>>) -> (Option<String>, Worker, Worker) {
+        let (input_backend, backend_error) = match backend::platform_backend() {
+            Ok(input_backend) => (input_backend, None),
+            Err(e) => {
+                warn!(error = %e, "input backend unavailable");
+                (Box::new(UnavailableBackend(e.clone())) as Box<dyn InputBackend>, Some(e.to_string()))
+            }
+        };
+        let hotkey = HotkeyManager::start_hotkey_listener(hotkey_vk.clone(), controls.running.clone());
+        let clicker = Clicker::start_clicker(input_backend, controls.clone());
+        (backend_error, clicker, hotkey)
+    }
+
+    // Pushes a stored profile into the values the click thread reads.
+    fn load(&mut self, profile: &Profile, windows: &[WindowInfo]) {
+        let controls = &self.controls;
+        *controls.interval_ms.write() = profile.interval_ms;
+        *controls.cps.write() = profile.cps;
+        *controls.timing.write() = profile.timing.clone();
+        *controls.stop_rules.write() = profile.stop;
+        *self.hotkey_vk.write() = profile.hotkey_vk;
+        controls.hold_shift.store(profile.hold_shift, Ordering::SeqCst);
+        controls.hold_ctrl.store(profile.hold_ctrl, Ordering::SeqCst);
+        *controls.click.write() = ClickAction {
+            button: profile.button,
+            count: profile.count,
+            point: profile.point,
+        };
+        *controls.selected_hwnd.write() = profile.target.as_ref().and_then(|target| target.find(windows));
+        self.script_source = profile.script.clone();
+        self.script_enabled = profile.script_enabled;
+        self.publish_script();
+    }
+
+    /// Writes the live values back into `profile` for saving.
+    pub fn store(&self, profile: &mut Profile, windows: &[WindowInfo]) {
+        // A saved target that has not reappeared yet is kept, not forgotten.
+        let target = self
+            .controls
+            .selected_hwnd
+            .read()
+            .and_then(|hwnd| windows.iter().find(|window| window.hwnd == hwnd))
+            .map(WindowTarget::from_window);
+
+        let controls = &self.controls;
+        profile.interval_ms = *controls.interval_ms.read();
+        profile.cps = *controls.cps.read();
+        profile.timing = controls.timing.read().clone();
+        profile.stop = *controls.stop_rules.read();
+        profile.hotkey_vk = *self.hotkey_vk.read();
+        profile.hold_shift = controls.hold_shift.load(Ordering::SeqCst);
+        profile.hold_ctrl = controls.hold_ctrl.load(Ordering::SeqCst);
+        let click = *controls.click.read();
+        profile.button = click.button;
+        profile.count = click.count;
+        profile.point = click.point;
+        if target.is_some() {
+            profile.target = target;
+        }
+        profile.script = self.script_source.clone();
+        profile.script_enabled = self.script_enabled;
+    }
+
+    /// Re-finds the saved target after the window list was refreshed.
+    pub fn reconnect(&self, profile: &Profile, windows: &[WindowInfo]) {
+        if self.controls.selected_hwnd.read().is_none()
+            && let Some(target) = &profile.target
+        {
+            *self.controls.selected_hwnd.write() = target.find(windows);
+        }
+    }
+
+    // Hands the edited script to the click thread if it compiles.
+    pub fn publish_script(&self) {
+        self.controls.publish_script(&self.script_source, self.script_enabled);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.controls.running.load(Ordering::SeqCst)
+    }
+
+    /// Stops clicking, lets go of every held key and joins both threads.
+    /// Also runs when the job is dropped.
+    pub fn shutdown(&mut self) {
+        self.controls.running.store(false, Ordering::SeqCst);
+        self.clicker.stop();
+        self.hotkey.stop();
+    }
+
+    /// Replaces both threads and reopens the input backend, e.g. after it
+    /// failed or the session changed. Settings and the running state carry
+    /// over.
+    pub fn restart(&mut self) {
+        let was_running = self.is_running();
+        info!(was_running, "restarting job threads");
+        self.shutdown();
+        let (backend_error, clicker, hotkey) = Self::start_workers(&self.controls, &self.hotkey_vk);
+        self.backend_error = backend_error;
+        self.clicker = clicker;
+        self.hotkey = hotkey;
+        self.controls.running.store(was_running && self.backend_error.is_none(), Ordering::SeqCst);
+    }
+}
+
+impl Drop for Job {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
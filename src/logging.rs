@@ -0,0 +1,180 @@
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::MakeWriter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{Registry, fmt, reload};
+
+/// Lines kept for the log view in the UI.
+const TAIL_LINES: usize = 500;
+/// Daily files kept in the log directory.
+const MAX_LOG_FILES: usize = 7;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 5] = [LogLevel::Error, LogLevel::Warn, LogLevel::Info, LogLevel::Debug, LogLevel::Trace];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LogLevel::Error => "ERROR",
+            LogLevel::Warn => "WARN",
+            LogLevel::Info => "INFO",
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Trace => "TRACE",
+        }
+    }
+
+    fn filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+            LogLevel::Trace => LevelFilter::TRACE,
+        }
+    }
+}
+
+/// Last formatted lines, shared between the subscriber and the UI.
+#[derive(Clone, Default)]
+pub struct LogTail(Arc<Mutex<VecDeque<String>>>);
+
+impl LogTail {
+    pub fn lines(&self) -> Vec<String> {
+        self.0.lock().iter().cloned().collect()
+    }
+
+    fn push(&self, text: &str) {
+        let mut lines = self.0.lock();
+        for line in text.lines().filter(|line| !line.is_empty()) {
+            if lines.len() == TAIL_LINES {
+                lines.pop_front();
+            }
+            lines.push_back(line.to_string());
+        }
+    }
+}
+
+/// Collects one formatted event and adds it to the tail when dropped, so an
+/// event written in pieces still shows up whole.
+pub struct TailWriter {
+    tail: LogTail,
+    buf: Vec<u8>,
+}
+
+impl Write for TailWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for TailWriter {
+    fn drop(&mut self) {
+        self.tail.push(&String::from_utf8_lossy(&self.buf));
+    }
+}
+
+impl<'a> MakeWriter<'a> for LogTail {
+    type Writer = TailWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        TailWriter { tail: self.clone(), buf: Vec::new() }
+    }
+}
+
+/// The installed subscriber: a daily rotating file in the config directory
+/// plus the in-memory tail, both behind one level that can be changed at run
+/// time. Keep it alive for the whole run so buffered lines reach the file.
+pub struct Logging {
+    level: reload::Handle<LevelFilter, Registry>,
+    tail: LogTail,
+    /// Where the files go, if the directory could be created.
+    pub dir: Option<PathBuf>,
+    _guard: Option<WorkerGuard>,
+}
+
+impl Logging {
+    pub fn dir() -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("beclicker").join("logs"))
+    }
+
+    pub fn init(level: LogLevel) -> Self {
+        let (filter, handle) = reload::Layer::new(level.filter());
+        let tail = LogTail::default();
+
+        let file = Self::dir().and_then(|dir| {
+            std::fs::create_dir_all(&dir).ok()?;
+            let appender = RollingFileAppender::builder()
+                .rotation(Rotation::DAILY)
+                .filename_prefix("beclicker")
+                .filename_suffix("log")
+                .max_log_files(MAX_LOG_FILES)
+                .build(&dir)
+                .ok()?;
+            Some((dir, tracing_appender::non_blocking(appender)))
+        });
+        let (dir, writer, guard) = match file {
+            Some((dir, (writer, guard))) => (Some(dir), Some(writer), Some(guard)),
+            None => (None, None, None),
+        };
+
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(writer.map(|writer| fmt::layer().with_ansi(false).with_writer(writer)))
+            .with(fmt::layer().with_ansi(false).with_target(false).without_time().with_writer(tail.clone()))
+            .try_init()
+            .ok();
+
+        Self {
+            level: handle,
+            tail,
+            dir,
+            _guard: guard,
+        }
+    }
+
+    pub fn set_level(&self, level: LogLevel) {
+        self.level.modify(|filter| *filter = level.filter()).ok();
+    }
+
+    pub fn tail(&self) -> &LogTail {
+        &self.tail
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tail_keeps_last_lines() {
+        let tail = LogTail::default();
+        for i in 0..TAIL_LINES + 3 {
+            writeln!(tail.make_writer(), "line {}", i).unwrap();
+        }
+        let lines = tail.lines();
+        assert_eq!(lines.len(), TAIL_LINES);
+        assert_eq!(lines[0], "line 3");
+        assert_eq!(lines.last().unwrap(), &format!("line {}", TAIL_LINES + 2));
+    }
+}
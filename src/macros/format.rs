@@ -0,0 +1,463 @@
+//! Text form of a macro, one step per line:
+//!
+//! ```text
+//! # lines starting with '#' are comments
+//! # move the pointer only, e.g. to hover
+//! move 120 45
+//! # buttons: left right middle x1 x2
+//! click left 120 45
+//! # separate press / release
+//! mouse down right 10 10
+//! mouse up right 10 10
+//! # keys: a-z, 0-9, f1-f24, enter, esc, space, ... or 0x41
+//! key down lctrl
+//! key up lctrl
+//! tap enter
+//! # escapes: \" \\ \n \t
+//! type "hello \"world\""
+//! # milliseconds
+//! wait 250
+//! # modifiers: shift ctrl alt
+//! hold shift
+//! release shift
+//! # repeats the lines up to the matching `end`
+//! loop 3
+//!   tap space
+//! end
+//! label again
+//! goto again
+//! ```
+//!
+//! Coordinates are client-area pixels of the target window. Comments take a
+//! whole line; indentation is ignored.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use super::{Macro, Modifier, Step, char_to_key};
+use crate::backend::MouseButton;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+const KEY_NAMES: &[(&str, u32)] = &[
+    ("backspace", 0x08),
+    ("tab", 0x09),
+    ("enter", 0x0D),
+    ("shift", 0x10),
+    ("ctrl", 0x11),
+    ("alt", 0x12),
+    ("pause", 0x13),
+    ("capslock", 0x14),
+    ("esc", 0x1B),
+    ("space", 0x20),
+    ("pgup", 0x21),
+    ("pgdn", 0x22),
+    ("end", 0x23),
+    ("home", 0x24),
+    ("left", 0x25),
+    ("up", 0x26),
+    ("right", 0x27),
+    ("down", 0x28),
+    ("insert", 0x2D),
+    ("delete", 0x2E),
+    ("lwin", 0x5B),
+    ("rwin", 0x5C),
+    ("lshift", 0xA0),
+    ("rshift", 0xA1),
+    ("lctrl", 0xA2),
+    ("rctrl", 0xA3),
+    ("lalt", 0xA4),
+    ("ralt", 0xA5),
+];
+
+const BUTTON_NAMES: &[(&str, MouseButton)] = &[
+    ("left", MouseButton::Left),
+    ("right", MouseButton::Right),
+    ("middle", MouseButton::Middle),
+    ("x1", MouseButton::X1),
+    ("x2", MouseButton::X2),
+];
+
+const MODIFIER_NAMES: &[(&str, Modifier)] = &[
+    ("shift", Modifier::Shift),
+    ("ctrl", Modifier::Ctrl),
+    ("alt", Modifier::Alt),
+];
+
+pub fn key_name(vk: u32) -> String {
+    match vk {
+        0x30..=0x39 | 0x41..=0x5A => char::from_u32(vk).unwrap().to_ascii_lowercase().to_string(),
+        0x60..=0x69 => format!("num{}", vk - 0x60),
+        0x70..=0x87 => format!("f{}", vk - 0x6F),
+        _ => KEY_NAMES
+            .iter()
+            .find(|(_, code)| *code == vk)
+            .map(|(name, _)| name.to_string())
+            .unwrap_or_else(|| format!("{:#04X}", vk)),
+    }
+}
+
+pub fn parse_key(token: &str) -> Option<u32> {
+    let token = token.to_ascii_lowercase();
+    if let Some(hex) = token.strip_prefix("0x") {
+        return u32::from_str_radix(hex, 16).ok().filter(|vk| (1..=0xFE).contains(vk));
+    }
+    let mut chars = token.chars();
+    if let (Some(c), None) = (chars.next(), chars.next())
+        && c.is_ascii_alphanumeric()
+    {
+        return Some(c.to_ascii_uppercase() as u32);
+    }
+    if let Some(n) = token.strip_prefix("num").and_then(|n| n.parse::<u32>().ok()) {
+        return (n <= 9).then_some(0x60 + n);
+    }
+    if let Some(n) = token.strip_prefix('f').and_then(|n| n.parse::<u32>().ok()) {
+        return (1..=24).contains(&n).then_some(0x6F + n);
+    }
+    KEY_NAMES.iter().find(|(name, _)| *name == token).map(|(_, vk)| *vk)
+}
+
+pub fn parse_button(token: &str) -> Option<MouseButton> {
+    lookup(BUTTON_NAMES, token)
+}
+
+pub fn button_name(button: MouseButton) -> &'static str {
+    name_of(BUTTON_NAMES, &button)
+}
+
+fn lookup<T: Copy>(table: &[(&str, T)], token: &str) -> Option<T> {
+    let token = token.to_ascii_lowercase();
+    table.iter().find(|(name, _)| *name == token).map(|(_, value)| *value)
+}
+
+fn name_of<T: PartialEq>(table: &[(&'static str, T)], value: &T) -> &'static str {
+    table.iter().find(|(_, v)| v == value).map(|(name, _)| *name).unwrap()
+}
+
+fn quote(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn unquote(text: &str) -> Result<String, String> {
+    let inner = text
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .filter(|_| text.len() >= 2)
+        .ok_or("text must be in double quotes")?;
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('"') => out.push('"'),
+                Some('\\') => out.push('\\'),
+                Some('n') => out.push('\n'),
+                Some('t') => out.push('\t'),
+                other => return Err(format!("unknown escape \\{}", other.map(String::from).unwrap_or_default())),
+            },
+            '"' => return Err("unescaped quote inside text".to_string()),
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+fn parse_step(line: &str) -> Result<Step, String> {
+    let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let rest = rest.trim();
+    if command.eq_ignore_ascii_case("type") {
+        let text = unquote(rest)?;
+        if let Some(c) = text.chars().find(|c| char_to_key(*c).is_none()) {
+            return Err(format!("character {:?} cannot be typed", c));
+        }
+        return Ok(Step::Type(text));
+    }
+
+    let args: Vec<&str> = rest.split_whitespace().collect();
+    let expect = |n: usize, usage: &str| -> Result<(), String> {
+        if args.len() == n { Ok(()) } else { Err(format!("expected `{}`", usage)) }
+    };
+    let number = |token: &str| token.parse::<i32>().map_err(|_| format!("`{}` is not a number", token));
+    let button = |token: &str| parse_button(token).ok_or_else(|| format!("unknown mouse button `{}`", token));
+    let key = |token: &str| parse_key(token).ok_or_else(|| format!("unknown key `{}`", token));
+    let modifier = |token: &str| lookup(MODIFIER_NAMES, token).ok_or_else(|| format!("unknown modifier `{}`", token));
+    let name = |token: &str| {
+        if token.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+            Ok(token.to_string())
+        } else {
+            Err(format!("label `{}` may only contain letters, digits, '_' and '-'", token))
+        }
+    };
+
+    match command.to_ascii_lowercase().as_str() {
+        "move" => {
+            expect(2, "move <x> <y>")?;
+            Ok(Step::Move { x: number(args[0])?, y: number(args[1])? })
+        }
+        "click" => {
+            expect(3, "click <button> <x> <y>")?;
+            Ok(Step::Click { button: button(args[0])?, x: number(args[1])?, y: number(args[2])? })
+        }
+        "mouse" => {
+            expect(4, "mouse down|up <button> <x> <y>")?;
+            let (button, x, y) = (button(args[1])?, number(args[2])?, number(args[3])?);
+            match args[0].to_ascii_lowercase().as_str() {
+                "down" => Ok(Step::MouseDown { button, x, y }),
+                "up" => Ok(Step::MouseUp { button, x, y }),
+                _ => Err("expected `mouse down` or `mouse up`".to_string()),
+            }
+        }
+        "key" => {
+            expect(2, "key down|up <key>")?;
+            let vk = key(args[1])?;
+            match args[0].to_ascii_lowercase().as_str() {
+                "down" => Ok(Step::KeyDown(vk)),
+                "up" => Ok(Step::KeyUp(vk)),
+                _ => Err("expected `key down` or `key up`".to_string()),
+            }
+        }
+        "tap" => {
+            expect(1, "tap <key>")?;
+            Ok(Step::KeyTap(key(args[0])?))
+        }
+        "wait" => {
+            expect(1, "wait <ms>")?;
+            args[0].parse().map(Step::Wait).map_err(|_| format!("`{}` is not a duration in ms", args[0]))
+        }
+        "hold" => {
+            expect(1, "hold <modifier>")?;
+            Ok(Step::Hold(modifier(args[0])?))
+        }
+        "release" => {
+            expect(1, "release <modifier>")?;
+            Ok(Step::Release(modifier(args[0])?))
+        }
+        "loop" => {
+            expect(1, "loop <count>")?;
+            match args[0].parse::<u32>() {
+                Ok(count) if count > 0 => Ok(Step::Loop(count)),
+                _ => Err(format!("loop count must be a positive number, got `{}`", args[0])),
+            }
+        }
+        "end" => {
+            expect(0, "end")?;
+            Ok(Step::EndLoop)
+        }
+        "label" => {
+            expect(1, "label <name>")?;
+            Ok(Step::Label(name(args[0])?))
+        }
+        "goto" => {
+            expect(1, "goto <name>")?;
+            Ok(Step::Goto(name(args[0])?))
+        }
+        _ => Err(format!("unknown command `{}`", command)),
+    }
+}
+
+impl Macro {
+    pub fn parse(text: &str) -> Result<Macro, ParseError> {
+        let mut steps = Vec::new();
+        let mut open_loops = Vec::new();
+        let mut labels = HashMap::new();
+        let mut gotos = Vec::new();
+
+        for (index, raw) in text.lines().enumerate() {
+            let line = index + 1;
+            let trimmed = raw.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            let step = parse_step(trimmed).map_err(|message| ParseError { line, message })?;
+            match &step {
+                Step::Loop(_) => open_loops.push(line),
+                Step::EndLoop => {
+                    open_loops.pop().ok_or_else(|| ParseError {
+                        line,
+                        message: "`end` without a matching `loop`".to_string(),
+                    })?;
+                }
+                Step::Label(name) => {
+                    if let Some(first) = labels.insert(name.clone(), line) {
+                        return Err(ParseError {
+                            line,
+                            message: format!("label `{}` is already defined on line {}", name, first),
+                        });
+                    }
+                }
+                Step::Goto(name) => gotos.push((line, name.clone())),
+                _ => {}
+            }
+            steps.push(step);
+        }
+
+        if let Some(line) = open_loops.pop() {
+            return Err(ParseError { line, message: "`loop` is never closed with `end`".to_string() });
+        }
+        if let Some((line, name)) = gotos.into_iter().find(|(_, name)| !labels.contains_key(name)) {
+            return Err(ParseError { line, message: format!("no label named `{}`", name) });
+        }
+        Ok(Macro { steps })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let mut depth = 0usize;
+        for step in &self.steps {
+            if *step == Step::EndLoop {
+                depth = depth.saturating_sub(1);
+            }
+            let line = match step {
+                Step::Move { x, y } => format!("move {} {}", x, y),
+                Step::Click { button, x, y } => format!("click {} {} {}", button_name(*button), x, y),
+                Step::MouseDown { button, x, y } => format!("mouse down {} {} {}", button_name(*button), x, y),
+                Step::MouseUp { button, x, y } => format!("mouse up {} {} {}", button_name(*button), x, y),
+                Step::KeyDown(vk) => format!("key down {}", key_name(*vk)),
+                Step::KeyUp(vk) => format!("key up {}", key_name(*vk)),
+                Step::KeyTap(vk) => format!("tap {}", key_name(*vk)),
+                Step::Type(text) => format!("type {}", quote(text)),
+                Step::Wait(ms) => format!("wait {}", ms),
+                Step::Hold(modifier) => format!("hold {}", name_of(MODIFIER_NAMES, modifier)),
+                Step::Release(modifier) => format!("release {}", name_of(MODIFIER_NAMES, modifier)),
+                Step::Loop(count) => format!("loop {}", count),
+                Step::EndLoop => "end".to_string(),
+                Step::Label(name) => format!("label {}", name),
+                Step::Goto(name) => format!("goto {}", name),
+            };
+            out.push_str(&"  ".repeat(depth));
+            out.push_str(&line);
+            out.push('\n');
+            if let Step::Loop(_) = step {
+                depth += 1;
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const SAMPLE: &str = "\
+# farm loop
+hold shift
+label start
+loop 3
+  move 118 40
+  click left 120 45
+  wait 150
+end
+mouse down right 10 20
+mouse up right 10 20
+key down lctrl
+tap a
+key up lctrl
+tap f6
+tap 0xFE
+type \"Hi, \\\"you\\\"\\n\"
+release shift
+goto start
+";
+
+    #[test]
+    fn parses_every_step_kind() {
+        let parsed = Macro::parse(SAMPLE).unwrap();
+        assert_eq!(
+            parsed.steps,
+            vec![
+                Step::Hold(Modifier::Shift),
+                Step::Label("start".to_string()),
+                Step::Loop(3),
+                Step::Move { x: 118, y: 40 },
+                Step::Click { button: MouseButton::Left, x: 120, y: 45 },
+                Step::Wait(150),
+                Step::EndLoop,
+                Step::MouseDown { button: MouseButton::Right, x: 10, y: 20 },
+                Step::MouseUp { button: MouseButton::Right, x: 10, y: 20 },
+                Step::KeyDown(0xA2),
+                Step::KeyTap(0x41),
+                Step::KeyUp(0xA2),
+                Step::KeyTap(0x75),
+                Step::KeyTap(0xFE),
+                Step::Type("Hi, \"you\"\n".to_string()),
+                Step::Release(Modifier::Shift),
+                Step::Goto("start".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn module_doc_example_parses() {
+        let source = include_str!("format.rs");
+        let example: String = source
+            .lines()
+            .skip_while(|line| *line != "//! ```text")
+            .skip(1)
+            .take_while(|line| *line != "//! ```")
+            .map(|line| format!("{}\n", line.trim_start_matches("//!").strip_prefix(' ').unwrap_or("")))
+            .collect();
+        let parsed = Macro::parse(&example).unwrap();
+        assert_eq!(parsed.steps.len(), 16);
+        assert_eq!(parsed.steps[7], Step::Type("hello \"world\"".to_string()));
+    }
+
+    #[test]
+    fn serializer_round_trips() {
+        let parsed = Macro::parse(SAMPLE).unwrap();
+        let text = parsed.to_text();
+        assert!(text.contains("\n  click left 120 45\n"), "{}", text);
+        assert_eq!(Macro::parse(&text).unwrap(), parsed);
+    }
+
+    #[test]
+    fn huge_waits_saturate_the_duration() {
+        let parsed = Macro::parse("loop 4294967295\nwait 18446744073709551615\nend\nwait 5").unwrap();
+        assert_eq!(parsed.duration(), Duration::from_millis(u64::MAX));
+    }
+
+    #[test]
+    fn errors_carry_line_numbers() {
+        let cases = [
+            ("wait 10\nclick left ten 5", 2, "not a number"),
+            ("tap nokey", 1, "unknown key"),
+            ("\n\nloop 2\ntap a", 3, "never closed"),
+            ("end", 1, "without a matching"),
+            ("label a\n# x\nlabel a", 3, "already defined on line 1"),
+            ("goto nowhere", 1, "no label"),
+            ("type \"Привет\"", 1, "cannot be typed"),
+            ("loop 0\nend", 1, "positive"),
+            ("jump 5", 1, "unknown command"),
+        ];
+        for (text, line, message) in cases {
+            let err = Macro::parse(text).unwrap_err();
+            assert_eq!(err.line, line, "{}", text);
+            assert!(err.message.contains(message), "{}: {}", text, err);
+        }
+    }
+}
@@ -0,0 +1,166 @@
+pub mod format;
+pub mod recorder;
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::backend::{MouseButton, VK_LCONTROL, VK_LSHIFT};
+
+pub const MACRO_EXTENSION: &str = "macro";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    Shift,
+    Ctrl,
+    Alt,
+}
+
+impl Modifier {
+    pub fn vk(self) -> u32 {
+        match self {
+            Modifier::Shift => VK_LSHIFT,
+            Modifier::Ctrl => VK_LCONTROL,
+            Modifier::Alt => 0xA4,
+        }
+    }
+}
+
+/// One action of a macro. Coordinates are in the target's client area, keys
+/// are virtual-key codes, as everywhere else. See `format` for the text form.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Move { x: i32, y: i32 },
+    Click { button: MouseButton, x: i32, y: i32 },
+    MouseDown { button: MouseButton, x: i32, y: i32 },
+    MouseUp { button: MouseButton, x: i32, y: i32 },
+    KeyDown(u32),
+    KeyUp(u32),
+    KeyTap(u32),
+    Type(String),
+    Wait(u64),
+    Hold(Modifier),
+    Release(Modifier),
+    /// Repeats the steps up to the matching `EndLoop` this many times.
+    Loop(u32),
+    EndLoop,
+    Label(String),
+    Goto(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Macro {
+    pub steps: Vec<Step>,
+}
+
+impl Macro {
+    /// Total of the waits in one run, loops included; jumps are not followed.
+    pub fn duration(&self) -> Duration {
+        let mut totals = vec![(0u64, 1u64)];
+        for step in &self.steps {
+            match step {
+                Step::Wait(ms) => {
+                    let total = &mut totals.last_mut().unwrap().0;
+                    *total = total.saturating_add(*ms);
+                }
+                Step::Loop(count) => totals.push((0, *count as u64)),
+                Step::EndLoop if totals.len() > 1 => {
+                    let (ms, count) = totals.pop().unwrap();
+                    let total = &mut totals.last_mut().unwrap().0;
+                    *total = total.saturating_add(ms.saturating_mul(count));
+                }
+                _ => {}
+            }
+        }
+        // Saturating: a hand-written file may ask for absurd waits or loop counts.
+        Duration::from_millis(totals.iter().fold(0u64, |sum, (ms, count)| sum.saturating_add(ms.saturating_mul(*count))))
+    }
+
+    /// Waits between consecutive mouse presses, in order of appearance; the
+    /// raw material for a timing histogram.
+    pub fn press_intervals(&self) -> Vec<u64> {
+        let mut intervals = Vec::new();
+        let mut since_press = None;
+        for step in &self.steps {
+            match step {
+                Step::Wait(ms) => since_press = since_press.map(|total: u64| total.saturating_add(*ms)),
+                Step::Click { .. } | Step::MouseDown { .. } => {
+                    intervals.extend(since_press);
+                    since_press = Some(0);
+                }
+                _ => {}
+            }
+        }
+        intervals
+    }
+
+    pub fn dir() -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("beclicker").join("macros"))
+    }
+
+    /// Names (file stems) of the macros saved in `dir()`, sorted.
+    pub fn list_saved() -> Vec<String> {
+        let Some(entries) = Self::dir().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                (path.extension()? == MACRO_EXTENSION).then_some(path.file_stem()?.to_str()?.to_owned())
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    pub fn saved_path(name: &str) -> Option<PathBuf> {
+        Some(Self::dir()?.join(format!("{}.{}", name, MACRO_EXTENSION)))
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self::parse(&std::fs::read_to_string(path)?)?)
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.to_text())?;
+        Ok(())
+    }
+}
+
+/// Virtual key and whether Shift is needed to type `c` on a US layout.
+pub fn char_to_key(c: char) -> Option<(u32, bool)> {
+    const SHIFTED_DIGITS: &str = ")!@#$%^&*(";
+    const PUNCTUATION: [(char, char, u32); 11] = [
+        ('-', '_', 0xBD),
+        ('=', '+', 0xBB),
+        ('[', '{', 0xDB),
+        (']', '}', 0xDD),
+        (';', ':', 0xBA),
+        ('\'', '"', 0xDE),
+        (',', '<', 0xBC),
+        ('.', '>', 0xBE),
+        ('/', '?', 0xBF),
+        ('\\', '|', 0xDC),
+        ('`', '~', 0xC0),
+    ];
+
+    let key = match c {
+        'a'..='z' => (c.to_ascii_uppercase() as u32, false),
+        'A'..='Z' => (c as u32, true),
+        '0'..='9' => (c as u32, false),
+        ' ' => (0x20, false),
+        '\n' => (0x0D, false),
+        '\t' => (0x09, false),
+        _ => {
+            if let Some(digit) = SHIFTED_DIGITS.find(c) {
+                (0x30 + digit as u32, true)
+            } else {
+                let &(plain, _, vk) = PUNCTUATION.iter().find(|(plain, shifted, _)| *plain == c || *shifted == c)?;
+                (vk, c != plain)
+            }
+        }
+    };
+    Some(key)
+}
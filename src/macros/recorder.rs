@@ -0,0 +1,159 @@
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}, mpsc::RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use parking_lot::Mutex;
+
+use super::{Macro, Step};
+use crate::hotkey_manager::{HotkeyManager, KeyEdge};
+use crate::window_manager::WindowManager;
+
+// Screen position to the target's client coordinates, or None when the
+// point lies outside its client area.
+type Locate = fn(isize, i32, i32) -> Option<(i32, i32)>;
+
+fn locate_in_window(hwnd: isize, x: i32, y: i32) -> Option<(i32, i32)> {
+    let (x, y) = WindowManager::screen_to_client(hwnd, x, y)?;
+    let (width, height) = WindowManager::client_size(hwnd)?;
+    (x >= 0 && y >= 0 && x < width && y < height).then_some((x, y))
+}
+
+/// Turns input edges into macro steps with the pauses between them.
+struct Recording {
+    target: isize,
+    ignore_vk: u32,
+    locate: Locate,
+    steps: Vec<Step>,
+    last: Option<Instant>,
+}
+
+impl Recording {
+    fn new(target: isize, ignore_vk: u32, locate: Locate) -> Self {
+        Self {
+            target,
+            ignore_vk,
+            locate,
+            steps: Vec::new(),
+            last: None,
+        }
+    }
+
+    // Clicks outside the target (on the app's own stop button, say) and the
+    // record hotkey are not part of the macro.
+    fn push(&mut self, edge: KeyEdge, at: Instant) {
+        let step = match edge {
+            KeyEdge::Pressed(vk) | KeyEdge::Released(vk) if vk == self.ignore_vk => return,
+            KeyEdge::Pressed(vk) => Step::KeyDown(vk),
+            KeyEdge::Released(vk) => Step::KeyUp(vk),
+            KeyEdge::ButtonPressed { button, x, y } => {
+                let Some((x, y)) = (self.locate)(self.target, x, y) else { return };
+                Step::MouseDown { button, x, y }
+            }
+            KeyEdge::ButtonReleased { button, x, y } => {
+                let Some((x, y)) = (self.locate)(self.target, x, y) else { return };
+                Step::MouseUp { button, x, y }
+            }
+        };
+
+        if let Some(last) = self.last {
+            let ms = at.saturating_duration_since(last).as_millis() as u64;
+            if ms > 0 {
+                self.steps.push(Step::Wait(ms));
+            }
+        }
+        self.last = Some(at);
+        self.steps.push(step);
+    }
+}
+
+/// Records global input against one target window on its own thread, so
+/// every event is timestamped as it arrives rather than once per UI frame.
+pub struct MacroRecorder {
+    stop: Arc<AtomicBool>,
+    recording: Arc<Mutex<Recording>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MacroRecorder {
+    pub fn start(target: isize, ignore_vk: u32) -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let recording = Arc::new(Mutex::new(Recording::new(target, ignore_vk, locate_in_window)));
+        let edges = HotkeyManager::subscribe();
+
+        let thread = {
+            let stop = stop.clone();
+            let recording = recording.clone();
+            std::thread::spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    match edges.recv_timeout(Duration::from_millis(50)) {
+                        Ok(edge) => recording.lock().push(edge, Instant::now()),
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+            })
+        };
+
+        Self { stop, recording, thread: Some(thread) }
+    }
+
+    pub fn step_count(&self) -> usize {
+        self.recording.lock().steps.len()
+    }
+
+    pub fn finish(mut self) -> Macro {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+        Macro { steps: std::mem::take(&mut self.recording.lock().steps) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MouseButton;
+
+    const HWND: isize = 0x1234;
+
+    // Pretends the target's client area starts at (100, 50) and is 200x100.
+    fn fake_locate(_hwnd: isize, x: i32, y: i32) -> Option<(i32, i32)> {
+        let (x, y) = (x - 100, y - 50);
+        (x >= 0 && y >= 0 && x < 200 && y < 100).then_some((x, y))
+    }
+
+    #[test]
+    fn records_rebased_clicks_keys_and_delays() {
+        let mut recording = Recording::new(HWND, 0x77, fake_locate);
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+
+        recording.push(KeyEdge::Pressed(0x77), at(0));
+        recording.push(KeyEdge::ButtonPressed { button: MouseButton::Left, x: 130, y: 70 }, at(200));
+        recording.push(KeyEdge::ButtonReleased { button: MouseButton::Left, x: 130, y: 70 }, at(260));
+        recording.push(KeyEdge::Pressed(0x41), at(260));
+        recording.push(KeyEdge::Released(0x41), at(400));
+        recording.push(KeyEdge::Released(0x77), at(450));
+
+        assert_eq!(
+            recording.steps,
+            vec![
+                Step::MouseDown { button: MouseButton::Left, x: 30, y: 20 },
+                Step::Wait(60),
+                Step::MouseUp { button: MouseButton::Left, x: 30, y: 20 },
+                Step::KeyDown(0x41),
+                Step::Wait(140),
+                Step::KeyUp(0x41),
+            ]
+        );
+    }
+
+    #[test]
+    fn clicks_outside_the_target_are_dropped() {
+        let mut recording = Recording::new(HWND, 0, fake_locate);
+        let now = Instant::now();
+        recording.push(KeyEdge::ButtonPressed { button: MouseButton::Right, x: 10, y: 10 }, now);
+        recording.push(KeyEdge::ButtonReleased { button: MouseButton::Right, x: 400, y: 70 }, now);
+        assert!(recording.steps.is_empty());
+    }
+}
@@ -1,13 +1,48 @@
 #![windows_subsystem = "windows"]
+mod api;
 mod app;
+mod backend;
+mod cli;
+mod window_filter;
 mod window_manager;
 mod hotkey_manager;
+mod job;
+mod logging;
 mod clicker;
+mod macros;
+mod schedule;
+mod scheduler;
+mod script;
+mod session;
+mod settings;
+mod stats;
+mod timing;
 mod ui;
+mod worker;
 
 use app::MyApp;
+use clap::Parser;
+use cli::Cli;
+use logging::Logging;
+use settings::Settings;
 
 fn main() -> eframe::Result<()> {
+    // Attached before parsing so help, --version and argument errors reach
+    // the console as well. Subcommands leave the window's settings and log
+    // files alone.
+    if std::env::args_os().len() > 1 {
+        cli::attach_console();
+        match Cli::try_parse() {
+            Ok(Cli { command: Some(command) }) => std::process::exit(cli::run(command)),
+            Ok(Cli { command: None }) => {}
+            Err(error) => error.exit(),
+        }
+    }
+
+    let settings = Settings::load();
+    let logging = Logging::init(settings.log_level);
+    tracing::info!(version = env!("CARGO_PKG_VERSION"), "starting");
+
     let options = eframe::NativeOptions {
         vsync: true,
         ..Default::default()
@@ -16,6 +51,6 @@ fn main() -> eframe::Result<()> {
     eframe::run_native(
         "🚀 Be Clicker",
         options,
-        Box::new(|_| Ok(Box::new(MyApp::new())))
+        Box::new(|_| Ok(Box::new(MyApp::new(settings, logging))))
     )
 }
\ No newline at end of file
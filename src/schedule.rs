@@ -0,0 +1,214 @@
+use chrono::{Datelike, NaiveDateTime, NaiveTime, TimeDelta, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::WindowTarget;
+
+/// How late an occurrence may be noticed and still count as on time; the app
+/// polls every second, so anything later means it was closed or asleep.
+const GRACE: TimeDelta = TimeDelta::seconds(60);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Trigger {
+    Once { at: NaiveDateTime },
+    /// Every listed weekday at `time`; an empty list means every day.
+    Weekly { time: NaiveTime, days: Vec<Weekday> },
+}
+
+impl Trigger {
+    /// The latest occurrence at or before `now`.
+    pub fn latest(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self {
+            Trigger::Once { at } => (*at <= now).then_some(*at),
+            Trigger::Weekly { time, days } => (0..=7)
+                .map(|back| now.date() - TimeDelta::days(back))
+                .filter(|date| days.is_empty() || days.contains(&date.weekday()))
+                .map(|date| date.and_time(*time))
+                .find(|at| *at <= now),
+        }
+    }
+
+    /// The first occurrence after `now`.
+    pub fn next(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self {
+            Trigger::Once { at } => (*at > now).then_some(*at),
+            Trigger::Weekly { time, days } => (0..=7)
+                .map(|ahead| now.date() + TimeDelta::days(ahead))
+                .filter(|date| days.is_empty() || days.contains(&date.weekday()))
+                .map(|date| date.and_time(*time))
+                .find(|at| *at > now),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Trigger::Once { at } => at.format("%d.%m %H:%M").to_string(),
+            Trigger::Weekly { time, days } => {
+                let days = match days.as_slice() {
+                    [] => "ежедневно".to_string(),
+                    [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri] => "пн–пт".to_string(),
+                    days => days.iter().map(|day| weekday_name(*day)).collect::<Vec<_>>().join(","),
+                };
+                format!("{} {}", days, time.format("%H:%M"))
+            }
+        }
+    }
+}
+
+pub fn weekday_name(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "пн",
+        Weekday::Tue => "вт",
+        Weekday::Wed => "ср",
+        Weekday::Thu => "чт",
+        Weekday::Fri => "пт",
+        Weekday::Sat => "сб",
+        Weekday::Sun => "вс",
+    }
+}
+
+/// What happens to an occurrence that passed while the app was closed or the
+/// machine was asleep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissedRun {
+    #[default]
+    Skip,
+    /// Start as soon as the miss is noticed (once, however many were missed).
+    RunLate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Due {
+    OnTime,
+    Late,
+    /// Late and skipped per `MissedRun::Skip`.
+    Missed,
+}
+
+/// Starts a profile on its own: "profile X at 09:00 on weekdays for 20 min".
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduleEntry {
+    pub enabled: bool,
+    /// Profile name, matched like `WindowTarget` titles.
+    pub profile: String,
+    /// Window to click instead of the profile's own target.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<WindowTarget>,
+    pub trigger: Trigger,
+    /// Stops the run after this long; unset leaves it to the profile.
+    #[serde(default)]
+    pub run_for_secs: Option<u64>,
+    #[serde(default)]
+    pub missed: MissedRun,
+    /// Occurrence handled last, fired or skipped; persisted so a restart
+    /// neither repeats it nor forgets the ones missed in between.
+    #[serde(default)]
+    pub last_fired: Option<NaiveDateTime>,
+}
+
+impl ScheduleEntry {
+    /// Claims the latest unhandled occurrence at `now`, if there is one.
+    /// Occurrences from before the entry was first polled never count, and a
+    /// disabled entry lets its occurrences pass silently.
+    pub fn poll(&mut self, now: NaiveDateTime) -> Option<Due> {
+        // Before the early return below, so an entry first polled ahead of
+        // its only occurrence still remembers when it started being watched.
+        let last = *self.last_fired.get_or_insert(now);
+        let occurrence = self.trigger.latest(now)?;
+        if occurrence <= last {
+            return None;
+        }
+        self.last_fired = Some(occurrence);
+        if !self.enabled {
+            return None;
+        }
+        Some(match (now - occurrence > GRACE, self.missed) {
+            (false, _) => Due::OnTime,
+            (true, MissedRun::RunLate) => Due::Late,
+            (true, MissedRun::Skip) => Due::Missed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    // 2024-05-06 is a Monday.
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 5, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn weekdays_at_nine() -> ScheduleEntry {
+        ScheduleEntry {
+            enabled: true,
+            profile: "Work".to_string(),
+            target: None,
+            trigger: Trigger::Weekly {
+                time: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                days: vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri],
+            },
+            run_for_secs: Some(1200),
+            missed: MissedRun::Skip,
+            last_fired: Some(at(3, 9, 0)),
+        }
+    }
+
+    #[test]
+    fn weekly_skips_weekend() {
+        let trigger = weekdays_at_nine().trigger;
+        assert_eq!(trigger.latest(at(5, 12, 0)), Some(at(3, 9, 0)));
+        assert_eq!(trigger.latest(at(6, 8, 59)), Some(at(3, 9, 0)));
+        assert_eq!(trigger.next(at(4, 10, 0)), Some(at(6, 9, 0)));
+        assert_eq!(trigger.describe(), "пн–пт 09:00");
+    }
+
+    #[test]
+    fn fires_once_per_occurrence() {
+        let mut entry = weekdays_at_nine();
+        assert_eq!(entry.poll(at(6, 8, 59)), None);
+        assert_eq!(entry.poll(at(6, 9, 0)), Some(Due::OnTime));
+        assert_eq!(entry.poll(at(6, 9, 0)), None);
+        assert_eq!(entry.poll(at(6, 17, 0)), None);
+        assert_eq!(entry.last_fired, Some(at(6, 9, 0)));
+    }
+
+    #[test]
+    fn missed_runs_follow_policy() {
+        // Asleep from Friday evening until Tuesday noon.
+        let mut skip = weekdays_at_nine();
+        assert_eq!(skip.poll(at(7, 12, 0)), Some(Due::Missed));
+        assert_eq!(skip.poll(at(7, 12, 1)), None);
+
+        let mut late = ScheduleEntry { missed: MissedRun::RunLate, ..weekdays_at_nine() };
+        assert_eq!(late.poll(at(7, 12, 0)), Some(Due::Late));
+        assert_eq!(late.poll(at(7, 12, 1)), None);
+    }
+
+    #[test]
+    fn new_and_disabled_entries_do_not_fire_for_the_past() {
+        let mut fresh = ScheduleEntry { last_fired: None, ..weekdays_at_nine() };
+        assert_eq!(fresh.poll(at(6, 10, 0)), None);
+        assert_eq!(fresh.poll(at(7, 9, 0)), Some(Due::OnTime));
+
+        let mut disabled = ScheduleEntry { enabled: false, ..weekdays_at_nine() };
+        assert_eq!(disabled.poll(at(6, 9, 0)), None);
+        disabled.enabled = true;
+        assert_eq!(disabled.poll(at(6, 9, 1)), None);
+
+        let mut once = ScheduleEntry { trigger: Trigger::Once { at: at(6, 9, 30) }, ..weekdays_at_nine() };
+        assert_eq!(once.poll(at(6, 9, 30)), Some(Due::OnTime));
+        assert_eq!(once.poll(at(8, 9, 30)), None);
+    }
+
+    #[test]
+    fn fresh_future_once_entry_fires() {
+        let mut once = ScheduleEntry { trigger: Trigger::Once { at: at(6, 9, 30) }, last_fired: None, ..weekdays_at_nine() };
+        assert_eq!(once.poll(at(6, 9, 0)), None);
+        assert_eq!(once.last_fired, Some(at(6, 9, 0)));
+        assert_eq!(once.poll(at(6, 9, 30)), Some(Due::OnTime));
+        assert_eq!(once.poll(at(6, 9, 31)), None);
+    }
+}
@@ -0,0 +1,192 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Last stretch before a deadline that is spun instead of slept, since OS
+/// sleeps overshoot by up to a timer tick.
+const SPIN_MARGIN: Duration = Duration::from_millis(2);
+/// Longest single sleep, so a stop request is noticed quickly.
+const SLEEP_SLICE: Duration = Duration::from_millis(10);
+/// Ticks kept for the measured rate.
+const RATE_WINDOW: usize = 64;
+
+/// How closely the clicker keeps to its schedule, for the UI.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct RateReport {
+    /// Rate the most recent period asks for.
+    pub target_cps: f64,
+    /// Ticks per second over the last `RATE_WINDOW` ticks; 0 until there
+    /// are two.
+    pub measured_cps: f64,
+    /// Average time a tick fired after its deadline.
+    pub mean_lateness: Duration,
+    pub max_lateness: Duration,
+}
+
+/// Paces ticks against absolute deadlines: each deadline is the previous one
+/// plus the period, so the time spent sending input and the overshoot of a
+/// sleep do not add up the way they do with `sleep(interval)` after every
+/// click.
+pub struct Pacer {
+    deadline: Option<Instant>,
+    fired: VecDeque<Instant>,
+    lateness: VecDeque<Duration>,
+    period: Duration,
+    _resolution: Option<TimerResolution>,
+}
+
+impl Pacer {
+    pub fn new() -> Self {
+        Self {
+            deadline: None,
+            fired: VecDeque::with_capacity(RATE_WINDOW),
+            lateness: VecDeque::with_capacity(RATE_WINDOW),
+            period: Duration::ZERO,
+            _resolution: None,
+        }
+    }
+
+    /// Waits for the next tick, `period` after the previous deadline; the
+    /// first tick after `reset` fires at once. Falling a whole period behind
+    /// (a slow send, a macro run) restarts the schedule from now instead of
+    /// firing a burst of catch-up ticks. Returns false if `running` was
+    /// cleared while waiting.
+    pub fn wait(&mut self, period: Duration, running: &AtomicBool) -> bool {
+        let now = Instant::now();
+        let deadline = match self.deadline {
+            Some(previous) if previous + period * 2 > now => previous + period,
+            _ => now,
+        };
+        self.deadline = Some(deadline);
+        self.period = period;
+        self._resolution.get_or_insert_with(TimerResolution::raise);
+
+        loop {
+            let now = Instant::now();
+            if !running.load(Ordering::SeqCst) {
+                return false;
+            }
+            let Some(left) = deadline.checked_duration_since(now).filter(|left| !left.is_zero()) else {
+                break;
+            };
+            if left > SPIN_MARGIN {
+                std::thread::sleep((left - SPIN_MARGIN).min(SLEEP_SLICE));
+            } else {
+                std::hint::spin_loop();
+            }
+        }
+
+        let now = Instant::now();
+        Self::push(&mut self.fired, now);
+        Self::push(&mut self.lateness, now - deadline);
+        true
+    }
+
+    /// Makes the next period count from now, for ticks whose own work (a
+    /// macro, a script) should be followed by a full pause.
+    pub fn rebase(&mut self) {
+        if self.deadline.is_some() {
+            self.deadline = Some(Instant::now());
+        }
+    }
+
+    /// Forgets the schedule and the measurements, e.g. when clicking stops.
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn report(&self) -> RateReport {
+        let measured_cps = match (self.fired.front(), self.fired.back()) {
+            (Some(first), Some(last)) if self.fired.len() > 1 && last > first => {
+                (self.fired.len() - 1) as f64 / (*last - *first).as_secs_f64()
+            }
+            _ => 0.0,
+        };
+        let total: Duration = self.lateness.iter().sum();
+        RateReport {
+            target_cps: if self.period.is_zero() { 0.0 } else { 1.0 / self.period.as_secs_f64() },
+            measured_cps,
+            mean_lateness: total.checked_div(self.lateness.len() as u32).unwrap_or_default(),
+            max_lateness: self.lateness.iter().max().copied().unwrap_or_default(),
+        }
+    }
+
+    fn push<T>(window: &mut VecDeque<T>, value: T) {
+        if window.len() == RATE_WINDOW {
+            window.pop_front();
+        }
+        window.push_back(value);
+    }
+}
+
+/// Raises the system timer resolution to 1 ms while clicking. Windows
+/// otherwise sleeps in ~15.6 ms steps, far more than `SPIN_MARGIN`.
+struct TimerResolution;
+
+impl TimerResolution {
+    fn raise() -> Self {
+        #[cfg(windows)]
+        unsafe {
+            windows::Win32::Media::timeBeginPeriod(1);
+        }
+        Self
+    }
+}
+
+#[cfg(windows)]
+impl Drop for TimerResolution {
+    fn drop(&mut self) {
+        unsafe {
+            windows::Win32::Media::timeEndPeriod(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_rate_despite_slow_work() {
+        let running = AtomicBool::new(true);
+        let mut pacer = Pacer::new();
+        let period = Duration::from_millis(4);
+        let start = Instant::now();
+        for _ in 0..50 {
+            assert!(pacer.wait(period, &running));
+            // Work that `sleep(interval)` after the click would add on top.
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        let elapsed = start.elapsed();
+        assert!(elapsed >= period * 49, "{:?}", elapsed);
+        assert!(elapsed < period * 49 + Duration::from_millis(40), "{:?}", elapsed);
+
+        let report = pacer.report();
+        assert_eq!(report.target_cps, 250.0);
+        assert!((200.0..=260.0).contains(&report.measured_cps), "{:?}", report);
+    }
+
+    #[test]
+    fn stall_restarts_schedule_without_burst() {
+        let running = AtomicBool::new(true);
+        let mut pacer = Pacer::new();
+        let period = Duration::from_millis(5);
+        pacer.wait(period, &running);
+        std::thread::sleep(Duration::from_millis(40));
+        pacer.wait(period, &running);
+        let resumed = Instant::now();
+        pacer.wait(period, &running);
+        assert!(resumed.elapsed() >= Duration::from_millis(4));
+    }
+
+    #[test]
+    fn stop_interrupts_wait() {
+        let running = AtomicBool::new(true);
+        let mut pacer = Pacer::new();
+        pacer.wait(Duration::from_secs(10), &running);
+        running.store(false, Ordering::SeqCst);
+        let start = Instant::now();
+        assert!(!pacer.wait(Duration::from_secs(10), &running));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+}
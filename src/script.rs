@@ -0,0 +1,283 @@
+//! Rhai scripts that replace the fixed click on the click thread.
+//!
+//! Functions available to a script:
+//!
+//! | function                 | does                                                   |
+//! |--------------------------|--------------------------------------------------------|
+//! | `click()`                | the click configured in the UI (button, count, point) |
+//! | `click(x, y)`            | that click at client pixel (x, y)                      |
+//! | `click(button, x, y)`    | `"left"`, `"right"`, `"middle"`, `"x1"`, `"x2"`        |
+//! | `key(name)` / `key(vk)`  | tap a key (`"a"`, `"enter"`, `"f6"`, `0x41`, ...)       |
+//! | `key_down(name)`, `key_up(name)` | press / release a key                          |
+//! | `sleep(ms)`              | pause; returns early when stopped                      |
+//! | `window_title()`         | title of the selected window, `""` if unknown          |
+//! | `is_running()`           | false once Stop or the hotkey was pressed              |
+//! | `pixel_at(x, y)`         | client pixel colour as `0xRRGGBB`, `-1` if unreadable  |
+//!
+//! The active profile's values are constants: `interval_ms`, `hold_shift`,
+//! `hold_ctrl`, `button`, `clicks`.
+//!
+//! The script runs once per tick, with the interval between runs. Stopping
+//! aborts it at the next operation, so even `loop {}` can be stopped.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+use rhai::{AST, Engine, EvalAltResult, Scope};
+
+use crate::clicker::{ClickAction, ClickLoop, ClickPoint, ClickerControls, ClientSizeCache};
+use crate::macros::format::{button_name, parse_button, parse_key};
+use crate::window_manager::WindowManager;
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+struct Host {
+    click_loop: Rc<RefCell<ClickLoop>>,
+    controls: ClickerControls,
+    client_sizes: RefCell<ClientSizeCache>,
+    /// Posts the backend refused since the runner last reported them.
+    failed: Cell<u32>,
+}
+
+impl Host {
+    fn target(&self) -> ScriptResult<isize> {
+        self.controls.selected_hwnd.read().ok_or_else(|| "no window selected".into())
+    }
+
+    fn click(&self, action: ClickAction) -> ScriptResult<()> {
+        let hwnd = self.target()?;
+        let client_size = match action.point {
+            ClickPoint::Percent { .. } => self.client_sizes.borrow_mut().get(hwnd),
+            ClickPoint::Pixels { .. } => None,
+        };
+        let failed = self.click_loop.borrow_mut().tick(
+            hwnd,
+            action,
+            client_size,
+            self.controls.hold_shift.load(Ordering::SeqCst),
+            self.controls.hold_ctrl.load(Ordering::SeqCst),
+        );
+        self.failed.set(self.failed.get() + failed);
+        Ok(())
+    }
+
+    fn click_at(&self, action: ClickAction, x: i64, y: i64) -> ScriptResult<()> {
+        let point = ClickPoint::Pixels { x: x as i32, y: y as i32 };
+        self.click(ClickAction { point, ..action })
+    }
+
+    fn key(&self, vk: u32, down: Option<bool>) -> ScriptResult<()> {
+        let hwnd = self.target()?;
+        let mut click_loop = self.click_loop.borrow_mut();
+        let failed = match down {
+            Some(down) => click_loop.key(hwnd, vk, down),
+            None => click_loop.key(hwnd, vk, true) + click_loop.key(hwnd, vk, false),
+        };
+        self.failed.set(self.failed.get() + failed);
+        Ok(())
+    }
+}
+
+fn key_code(name: &str) -> ScriptResult<u32> {
+    parse_key(name).ok_or_else(|| format!("unknown key `{}`", name).into())
+}
+
+// Virtual-key codes run from 0x01 to 0xFE.
+fn vk_code(vk: i64) -> ScriptResult<u32> {
+    u32::try_from(vk)
+        .ok()
+        .filter(|vk| (1..=0xFE).contains(vk))
+        .ok_or_else(|| format!("key code {} is out of range 1..=254", vk).into())
+}
+
+pub struct ScriptRunner {
+    engine: Engine,
+    host: Rc<Host>,
+    controls: ClickerControls,
+    compiled: Option<(String, AST)>,
+}
+
+impl ScriptRunner {
+    pub fn new(click_loop: Rc<RefCell<ClickLoop>>, controls: ClickerControls) -> Self {
+        let mut engine = Engine::new();
+        let host = Rc::new(Host {
+            click_loop,
+            controls: controls.clone(),
+            client_sizes: RefCell::new(ClientSizeCache::new()),
+            failed: Cell::new(0),
+        });
+
+        let running = controls.running.clone();
+        engine.on_progress(move |_| (!running.load(Ordering::SeqCst)).then(|| "stopped".into()));
+
+        let h = host.clone();
+        engine.register_fn("click", move || h.click(*h.controls.click.read()));
+        let h = host.clone();
+        engine.register_fn("click", move |x: i64, y: i64| h.click_at(*h.controls.click.read(), x, y));
+        let h = host.clone();
+        engine.register_fn("click", move |button: &str, x: i64, y: i64| -> ScriptResult<()> {
+            let button = parse_button(button).ok_or_else(|| format!("unknown mouse button `{}`", button))?;
+            h.click_at(ClickAction { button, ..*h.controls.click.read() }, x, y)
+        });
+
+        let h = host.clone();
+        engine.register_fn("key", move |name: &str| h.key(key_code(name)?, None));
+        let h = host.clone();
+        engine.register_fn("key", move |vk: i64| h.key(vk_code(vk)?, None));
+        let h = host.clone();
+        engine.register_fn("key_down", move |name: &str| h.key(key_code(name)?, Some(true)));
+        let h = host.clone();
+        engine.register_fn("key_up", move |name: &str| h.key(key_code(name)?, Some(false)));
+
+        let h = host.clone();
+        engine.register_fn("sleep", move |ms: i64| {
+            ClickLoop::wait(Duration::from_millis(ms.max(0) as u64), &h.controls.running);
+        });
+        let h = host.clone();
+        engine.register_fn("is_running", move || h.controls.running.load(Ordering::SeqCst));
+        let h = host.clone();
+        engine.register_fn("window_title", move || -> String {
+            h.target().ok().and_then(WindowManager::window_title).unwrap_or_default()
+        });
+        let h = host.clone();
+        engine.register_fn("pixel_at", move |x: i64, y: i64| -> i64 {
+            h.target()
+                .ok()
+                .and_then(|hwnd| WindowManager::pixel_at(hwnd, x as i32, y as i32))
+                .map_or(-1, i64::from)
+        });
+
+        Self { engine, host, controls, compiled: None }
+    }
+
+    /// How many posts the backend refused since the last call, e.g. over
+    /// one run.
+    pub fn take_failed(&self) -> u32 {
+        self.host.failed.take()
+    }
+
+    /// Checks that `source` compiles, without running it. Host functions are
+    /// only looked up when called, so a plain engine is enough.
+    pub fn check(source: &str) -> Result<(), String> {
+        Engine::new().compile(source).map(drop).map_err(|e| e.to_string())
+    }
+
+    /// Runs `source` once, compiling it only when it changed since the last
+    /// run. Being stopped is not an error.
+    pub fn run(&mut self, source: &str) -> Result<(), String> {
+        if self.compiled.as_ref().is_none_or(|(compiled, _)| compiled != source) {
+            let ast = self.engine.compile(source).map_err(|e| e.to_string())?;
+            self.compiled = Some((source.to_string(), ast));
+        }
+        let (_, ast) = self.compiled.as_ref().unwrap();
+
+        let action = *self.controls.click.read();
+        let mut scope = Scope::new();
+        scope.push_constant("interval_ms", *self.controls.interval_ms.read() as i64);
+        scope.push_constant("hold_shift", self.controls.hold_shift.load(Ordering::SeqCst));
+        scope.push_constant("hold_ctrl", self.controls.hold_ctrl.load(Ordering::SeqCst));
+        scope.push_constant("button", button_name(action.button).to_string());
+        scope.push_constant("clicks", action.count.presses() as i64);
+
+        match self.engine.run_ast_with_scope(&mut scope, ast) {
+            Ok(()) => Ok(()),
+            Err(e) if matches!(*e, EvalAltResult::ErrorTerminated(..)) => Ok(()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MouseButton;
+    use crate::backend::recording::{InputEvent, RecordingBackend};
+    use parking_lot::RwLock;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicBool;
+    use std::time::Instant;
+
+    const HWND: isize = 0x1234;
+
+    fn runner() -> (ScriptRunner, RecordingBackend, ClickerControls) {
+        let recorder = RecordingBackend::new();
+        let controls = ClickerControls {
+            running: Arc::new(AtomicBool::new(true)),
+            selected_hwnd: Arc::new(RwLock::new(Some(HWND))),
+            interval_ms: Arc::new(RwLock::new(250)),
+            ..ClickerControls::default()
+        };
+        let click_loop = Rc::new(RefCell::new(ClickLoop::new(Box::new(recorder.clone()))));
+        (ScriptRunner::new(click_loop, controls.clone()), recorder, controls)
+    }
+
+    #[test]
+    fn script_clicks_and_taps_keys() {
+        let (mut runner, recorder, _) = runner();
+        runner
+            .run(r#"
+                for i in 0..2 { click(10 * i, 5); }
+                if interval_ms == 250 && button == "left" { key("enter"); }
+                click("right", 1, 2);
+            "#)
+            .unwrap();
+
+        let click = |button, x, y| {
+            [InputEvent::MouseDown { target: HWND, button, x, y }, InputEvent::MouseUp { target: HWND, button, x, y }]
+        };
+        let expected: Vec<InputEvent> = [
+            &click(MouseButton::Left, 0, 5)[..],
+            &click(MouseButton::Left, 10, 5)[..],
+            &[InputEvent::KeyDown { target: HWND, vk: 0x0D }, InputEvent::KeyUp { target: HWND, vk: 0x0D }][..],
+            &click(MouseButton::Right, 1, 2)[..],
+        ]
+        .concat();
+        assert_eq!(recorder.trace(), expected);
+        assert_eq!(runner.take_failed(), 0);
+    }
+
+    #[test]
+    fn refused_posts_are_counted() {
+        use crate::backend::{BackendError, UnavailableBackend};
+
+        let backend = UnavailableBackend(BackendError("gone".to_string()));
+        let click_loop = Rc::new(RefCell::new(ClickLoop::new(Box::new(backend))));
+        let controls = ClickerControls {
+            running: Arc::new(AtomicBool::new(true)),
+            selected_hwnd: Arc::new(RwLock::new(Some(HWND))),
+            ..ClickerControls::default()
+        };
+        let mut runner = ScriptRunner::new(click_loop, controls);
+        runner.run(r#"click(); key("a"); key_down("b");"#).unwrap();
+        assert_eq!(runner.take_failed(), 5);
+        assert_eq!(runner.take_failed(), 0);
+    }
+
+    #[test]
+    fn runaway_loop_stops_with_running_flag() {
+        let (mut runner, _, controls) = runner();
+        let stopper = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            controls.running.store(false, Ordering::SeqCst);
+        });
+        let started = Instant::now();
+        assert_eq!(runner.run("let n = 0; loop { n += 1; }"), Ok(()));
+        stopper.join().unwrap();
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn errors_are_reported() {
+        let (mut runner, recorder, _) = runner();
+        assert!(runner.run("click(").is_err());
+        let err = runner.run(r#"click("sideways", 1, 1)"#).unwrap_err();
+        assert!(err.contains("unknown mouse button"), "{}", err);
+        for code in ["key(-1)", "key(0)", "key(4294967361)"] {
+            let err = runner.run(code).unwrap_err();
+            assert!(err.contains("out of range"), "{}: {}", code, err);
+        }
+        assert!(recorder.trace().is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+use std::time::{Duration, Instant};
+use chrono::{Local, NaiveDateTime, NaiveTime, TimeDelta};
+use serde::{Deserialize, Serialize};
+
+/// Optional limits that end a clicking session on their own. Unset rules
+/// never fire; the first one reached wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StopRules {
+    /// Ticks per session: clicks, macro runs or script runs.
+    pub max_clicks: Option<u64>,
+    pub max_duration_secs: Option<u64>,
+    /// Local time of day; if it has already passed when the session starts,
+    /// it means the same time tomorrow.
+    pub stop_at: Option<NaiveTime>,
+}
+
+impl StopRules {
+    /// Both sets of rules at once: whichever limit is reached first.
+    pub fn tightest(self, other: StopRules) -> StopRules {
+        fn min<T: Ord>(a: Option<T>, b: Option<T>) -> Option<T> {
+            match (a, b) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            }
+        }
+        StopRules {
+            max_clicks: min(self.max_clicks, other.max_clicks),
+            max_duration_secs: min(self.max_duration_secs, other.max_duration_secs),
+            // Which of two times of day comes first depends on the start;
+            // the run's own limit is the one that is kept.
+            stop_at: other.stop_at.or(self.stop_at),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    ClickLimit,
+    TimeLimit,
+    StopTime,
+}
+
+/// Snapshot of the running (or last) session for the UI.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct SessionStatus {
+    pub clicks: u64,
+    pub elapsed: Duration,
+    /// Time left until the nearest time-based rule, if any is set.
+    pub remaining: Option<Duration>,
+    /// Set once a rule has fired.
+    pub stopped: Option<StopReason>,
+}
+
+/// One run from start to stop, counted by the click thread.
+pub struct Session {
+    started: Instant,
+    started_at: NaiveDateTime,
+    pub clicks: u64,
+}
+
+impl Session {
+    pub fn start() -> Self {
+        Self::start_at(Instant::now(), Local::now().naive_local())
+    }
+
+    fn start_at(started: Instant, started_at: NaiveDateTime) -> Self {
+        Self { started, started_at, clicks: 0 }
+    }
+
+    pub fn status(&self, rules: &StopRules) -> SessionStatus {
+        self.status_at(rules, self.started.elapsed(), Local::now().naive_local())
+    }
+
+    fn status_at(&self, rules: &StopRules, elapsed: Duration, now: NaiveDateTime) -> SessionStatus {
+        let by_duration = rules
+            .max_duration_secs
+            .map(|secs| (Duration::from_secs(secs).saturating_sub(elapsed), StopReason::TimeLimit));
+        let by_time = rules.stop_at.map(|time| {
+            let mut deadline = self.started_at.date().and_time(time);
+            if deadline <= self.started_at {
+                deadline += TimeDelta::days(1);
+            }
+            let left = (deadline - now).to_std().unwrap_or_default();
+            (left, StopReason::StopTime)
+        });
+        let nearest = by_duration.into_iter().chain(by_time).min_by_key(|(left, _)| *left);
+
+        let stopped = match (rules.max_clicks, nearest) {
+            (Some(max), _) if self.clicks >= max => Some(StopReason::ClickLimit),
+            (_, Some((left, reason))) if left.is_zero() => Some(reason),
+            _ => None,
+        };
+        SessionStatus {
+            clicks: self.clicks,
+            elapsed,
+            remaining: nearest.map(|(left, _)| left),
+            stopped,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 5, 1).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn session(clicks: u64, started_at: NaiveDateTime) -> Session {
+        Session { clicks, ..Session::start_at(Instant::now(), started_at) }
+    }
+
+    #[test]
+    fn no_rules_never_stop() {
+        let status = session(1_000_000, at(9, 0)).status_at(&StopRules::default(), Duration::from_secs(86_400), at(9, 0));
+        assert_eq!(status.stopped, None);
+        assert_eq!(status.remaining, None);
+    }
+
+    #[test]
+    fn click_limit_fires_at_count() {
+        let rules = StopRules { max_clicks: Some(10), ..StopRules::default() };
+        assert_eq!(session(9, at(9, 0)).status_at(&rules, Duration::ZERO, at(9, 0)).stopped, None);
+        assert_eq!(session(10, at(9, 0)).status_at(&rules, Duration::ZERO, at(9, 0)).stopped, Some(StopReason::ClickLimit));
+    }
+
+    #[test]
+    fn nearest_time_rule_wins() {
+        let rules = StopRules {
+            max_duration_secs: Some(600),
+            stop_at: Some(NaiveTime::from_hms_opt(9, 5, 0).unwrap()),
+            ..StopRules::default()
+        };
+        let running = session(0, at(9, 0)).status_at(&rules, Duration::from_secs(60), at(9, 1));
+        assert_eq!(running.remaining, Some(Duration::from_secs(240)));
+        assert_eq!(running.stopped, None);
+
+        let done = session(0, at(9, 0)).status_at(&rules, Duration::from_secs(300), at(9, 5));
+        assert_eq!(done.stopped, Some(StopReason::StopTime));
+
+        let rules = StopRules { max_duration_secs: Some(60), ..rules };
+        let done = session(0, at(9, 0)).status_at(&rules, Duration::from_secs(61), at(9, 1));
+        assert_eq!(done.stopped, Some(StopReason::TimeLimit));
+        assert_eq!(done.remaining, Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn past_stop_time_means_tomorrow() {
+        let rules = StopRules { stop_at: Some(NaiveTime::from_hms_opt(8, 0, 0).unwrap()), ..StopRules::default() };
+        let status = session(0, at(9, 0)).status_at(&rules, Duration::ZERO, at(9, 0));
+        assert_eq!(status.remaining, Some(Duration::from_secs(23 * 3600)));
+        assert_eq!(status.stopped, None);
+    }
+}
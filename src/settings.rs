@@ -0,0 +1,438 @@
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::api::ApiSettings;
+use crate::backend::MouseButton;
+use crate::clicker::{ClickCount, ClickPoint};
+use crate::logging::LogLevel;
+use crate::schedule::ScheduleEntry;
+use crate::session::StopRules;
+use crate::timing::Timing;
+use crate::window_filter::WindowFilter;
+use crate::window_manager::WindowInfo;
+
+pub const SETTINGS_VERSION: u32 = 2;
+
+/// What is needed to find the selected window again after a restart. Window
+/// handles change every launch, so the match is by title (and class/process
+/// when known).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowTarget {
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub class: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub process: Option<String>,
+}
+
+impl WindowTarget {
+    #[cfg(test)]
+    pub fn from_title(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            class: None,
+            process: None,
+        }
+    }
+
+    /// Remembers the class and process as well, so that a window among
+    /// several with the same title is found again.
+    pub fn from_window(window: &WindowInfo) -> Self {
+        Self {
+            title: window.title.clone(),
+            class: window.class.clone(),
+            process: window.process.clone(),
+        }
+    }
+
+    /// Whether `window` has this title and a class and process that agree
+    /// (where both sides know them).
+    pub fn matches(&self, window: &WindowInfo) -> bool {
+        window.title == self.title && self.same_app(window)
+    }
+
+    fn same_app(&self, window: &WindowInfo) -> bool {
+        fn agrees(wanted: &Option<String>, actual: &Option<String>) -> bool {
+            match (wanted, actual) {
+                (Some(wanted), Some(actual)) => wanted == actual,
+                _ => true,
+            }
+        }
+        agrees(&self.class, &window.class) && agrees(&self.process, &window.process)
+    }
+
+    /// The first window this target matches. Failing that, the only window of that class and
+    /// process, for apps that put changing text in their title.
+    pub fn find(&self, windows: &[WindowInfo]) -> Option<isize> {
+        if let Some(window) = windows.iter().find(|window| self.matches(window)) {
+            return Some(window.hwnd);
+        }
+        if self.class.is_none() || self.process.is_none() {
+            return None;
+        }
+        let mut candidates = windows
+            .iter()
+            .filter(|window| window.class.is_some() && window.process.is_some())
+            .filter(|window| self.same_app(window));
+        match (candidates.next(), candidates.next()) {
+            (Some(window), None) => Some(window.hwnd),
+            _ => None,
+        }
+    }
+}
+
+/// One named setup: everything that changes when switching between tasks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    pub interval_ms: u64,
+    /// Clicks per second; when set it replaces `interval_ms`.
+    pub cps: Option<f64>,
+    /// How each pause is drawn; the default keeps `interval_ms` fixed.
+    pub timing: Timing,
+    pub hotkey_vk: u32,
+    pub hold_shift: bool,
+    pub hold_ctrl: bool,
+    pub button: MouseButton,
+    pub count: ClickCount,
+    pub point: ClickPoint,
+    pub target: Option<WindowTarget>,
+    pub stop: StopRules,
+    /// Rhai script run instead of the click while `script_enabled`.
+    pub script: String,
+    pub script_enabled: bool,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: "Основной".to_string(),
+            interval_ms: 500,
+            cps: None,
+            timing: Timing::default(),
+            hotkey_vk: 0x75,
+            hold_shift: false,
+            hold_ctrl: false,
+            button: MouseButton::Left,
+            count: ClickCount::Single,
+            point: ClickPoint::default(),
+            target: None,
+            stop: StopRules::default(),
+            script: String::new(),
+            script_enabled: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub profiles: Vec<Profile>,
+    pub active_profile: usize,
+    /// Global hotkey that cycles to the next profile; 0 when unset.
+    pub profile_hotkey_vk: u32,
+    /// Global hotkey that starts and stops macro recording; 0 when unset.
+    pub macro_hotkey_vk: u32,
+    /// Automatic starts, checked by the app once a second.
+    pub schedules: Vec<ScheduleEntry>,
+    pub log_level: LogLevel,
+    pub api: ApiSettings,
+    pub window_filter: WindowFilter,
+    /// Windows picked in the window picker, most recent first.
+    pub recent_windows: Vec<WindowTarget>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            version: SETTINGS_VERSION,
+            profiles: vec![Profile::default()],
+            active_profile: 0,
+            profile_hotkey_vk: 0,
+            macro_hotkey_vk: 0,
+            schedules: Vec::new(),
+            log_level: LogLevel::default(),
+            api: ApiSettings::default(),
+            window_filter: WindowFilter::default(),
+            recent_windows: Vec::new(),
+        }
+    }
+}
+
+// Version 1 kept a single flat set of values; it becomes the first profile.
+#[derive(Deserialize)]
+#[serde(default)]
+struct SettingsV1 {
+    interval_ms: u64,
+    hotkey_vk: u32,
+    hold_shift: bool,
+    hold_ctrl: bool,
+    target: Option<WindowTarget>,
+}
+
+impl Default for SettingsV1 {
+    fn default() -> Self {
+        let profile = Profile::default();
+        Self {
+            interval_ms: profile.interval_ms,
+            hotkey_vk: profile.hotkey_vk,
+            hold_shift: profile.hold_shift,
+            hold_ctrl: profile.hold_ctrl,
+            target: None,
+        }
+    }
+}
+
+impl From<SettingsV1> for Settings {
+    fn from(v1: SettingsV1) -> Self {
+        Self {
+            profiles: vec![Profile {
+                interval_ms: v1.interval_ms,
+                hotkey_vk: v1.hotkey_vk,
+                hold_shift: v1.hold_shift,
+                hold_ctrl: v1.hold_ctrl,
+                target: v1.target,
+                ..Profile::default()
+            }],
+            ..Settings::default()
+        }
+    }
+}
+
+impl Settings {
+    pub fn path() -> Option<PathBuf> {
+        Some(dirs::config_dir()?.join("beclicker").join("settings.json"))
+    }
+
+    /// Loads the saved settings, falling back to defaults when there is no
+    /// file yet or it cannot be read.
+    pub fn load() -> Self {
+        Self::path().map(|path| Self::load_or_back_up(&path)).unwrap_or_default()
+    }
+
+    // A file that exists but cannot be read (a newer version, a broken hand
+    // edit) is moved to `settings.json.bak`, so the first save of the
+    // defaults does not destroy the user's profiles.
+    fn load_or_back_up(path: &Path) -> Self {
+        match Self::load_from(path) {
+            Ok(settings) => settings,
+            Err(_) if !path.exists() => Self::default(),
+            Err(_) => {
+                std::fs::rename(path, path.with_extension("json.bak")).ok();
+                Self::default()
+            }
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = std::fs::read_to_string(path)?;
+        let value: serde_json::Value = serde_json::from_str(&text)?;
+        let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(1) as u32;
+        let settings: Settings = match version {
+            1 => serde_json::from_value::<SettingsV1>(value)?.into(),
+            SETTINGS_VERSION => serde_json::from_value(value)?,
+            _ => return Err(format!("settings version {} is newer than supported {}", version, SETTINGS_VERSION).into()),
+        };
+        Ok(settings.normalized())
+    }
+
+    // Guarantees at least one profile and an in-range active index, so the
+    // rest of the app never has to check.
+    fn normalized(mut self) -> Self {
+        self.version = SETTINGS_VERSION;
+        if self.profiles.is_empty() {
+            self.profiles.push(Profile::default());
+        }
+        if self.active_profile >= self.profiles.len() {
+            self.active_profile = 0;
+        }
+        self
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::path().ok_or("no config directory")?;
+        self.save_to(&path)
+    }
+
+    // Written to a temp file first so a crash mid-write never leaves a
+    // truncated settings file behind.
+    pub fn save_to(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::{MissedRun, Trigger};
+    use crate::timing::IntervalDistribution;
+    use crate::window_filter::WindowSort;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("beclicker-test-{}-{}", std::process::id(), name))
+            .join("settings.json")
+    }
+
+    #[test]
+    fn round_trips_through_file() {
+        let path = temp_path("round-trip");
+        let settings = Settings {
+            profiles: vec![
+                Profile::default(),
+                Profile {
+                    name: "Fast".to_string(),
+                    interval_ms: 60,
+                    cps: Some(120.0),
+                    timing: Timing {
+                        distribution: IntervalDistribution::Normal { mean_ms: 60.0, std_dev_ms: 12.5 },
+                        seed: Some(9),
+                    },
+                    hotkey_vk: 0x52,
+                    hold_ctrl: true,
+                    button: MouseButton::X2,
+                    count: ClickCount::Double,
+                    point: ClickPoint::Percent { x: 50.0, y: 75.5 },
+                    target: Some(WindowTarget::from_title("Test App")),
+                    stop: StopRules {
+                        max_clicks: Some(1000),
+                        stop_at: chrono::NaiveTime::from_hms_opt(18, 30, 0),
+                        ..StopRules::default()
+                    },
+                    script: "click(); sleep(50);".to_string(),
+                    script_enabled: true,
+                    ..Profile::default()
+                },
+            ],
+            active_profile: 1,
+            profile_hotkey_vk: 0x77,
+            macro_hotkey_vk: 0x78,
+            schedules: vec![ScheduleEntry {
+                enabled: true,
+                profile: "Fast".to_string(),
+                target: None,
+                trigger: Trigger::Weekly {
+                    time: chrono::NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                    days: vec![chrono::Weekday::Mon, chrono::Weekday::Fri],
+                },
+                run_for_secs: Some(1200),
+                missed: MissedRun::RunLate,
+                last_fired: None,
+            }],
+            log_level: LogLevel::Debug,
+            api: ApiSettings {
+                enabled: true,
+                port: 9000,
+                unix_socket: Some("/tmp/beclicker.sock".to_string()),
+                token: "secret".to_string(),
+            },
+            window_filter: WindowFilter {
+                regex: true,
+                sort: WindowSort::Recent,
+                hide_tool: false,
+                ..WindowFilter::default()
+            },
+            recent_windows: vec![WindowTarget {
+                title: "Game".to_string(),
+                class: Some("UnityWndClass".to_string()),
+                process: Some("game.exe".to_string()),
+            }],
+            ..Settings::default()
+        };
+        settings.save_to(&path).unwrap();
+        assert_eq!(Settings::load_from(&path).unwrap(), settings);
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn unreadable_file_is_kept_as_backup() {
+        let path = temp_path("unreadable");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let text = r#"{"version": 99, "profiles": []}"#;
+        std::fs::write(&path, text).unwrap();
+
+        assert_eq!(Settings::load_or_back_up(&path), Settings::default());
+        assert!(!path.exists());
+        assert_eq!(std::fs::read_to_string(path.with_extension("json.bak")).unwrap(), text);
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn version_1_becomes_first_profile() {
+        let path = temp_path("v1");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, r#"{"version": 1, "interval_ms": 1500, "hold_ctrl": true, "target": {"title": "Tool"}}"#)
+            .unwrap();
+
+        let settings = Settings::load_from(&path).unwrap();
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(
+            settings.profiles,
+            vec![Profile {
+                interval_ms: 1500,
+                hold_ctrl: true,
+                target: Some(WindowTarget::from_title("Tool")),
+                ..Profile::default()
+            }]
+        );
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn empty_profile_list_is_repaired() {
+        let path = temp_path("empty");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, r#"{"version": 2, "profiles": [], "active_profile": 3}"#).unwrap();
+        assert_eq!(Settings::load_from(&path).unwrap(), Settings::default());
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let path = temp_path("newer");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, r#"{"version": 99}"#).unwrap();
+        assert!(Settings::load_from(&path).is_err());
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    fn window(hwnd: isize, title: &str, class: &str, process: &str) -> WindowInfo {
+        WindowInfo {
+            hwnd,
+            title: title.to_string(),
+            class: Some(class.to_string()),
+            process: Some(process.to_string()),
+            ..WindowInfo::default()
+        }
+    }
+
+    #[test]
+    fn target_matches_by_title() {
+        let windows = vec![window(1, "Editor", "Edit", "edit.exe"), window(2, "Test App", "App", "app.exe")];
+        assert_eq!(WindowTarget::from_title("Test App").find(&windows), Some(2));
+        assert_eq!(WindowTarget::from_title("Gone").find(&windows), None);
+    }
+
+    #[test]
+    fn target_tells_same_titles_apart_by_process() {
+        let windows = vec![
+            window(1, "New Tab", "Chrome_WidgetWin_1", "chrome.exe"),
+            window(2, "New Tab", "MozillaWindowClass", "firefox.exe"),
+        ];
+        assert_eq!(WindowTarget::from_window(&windows[1]).find(&windows), Some(2));
+
+        // The title changed, but only one window of that app is open.
+        let renamed = WindowTarget { title: "Old title".to_string(), ..WindowTarget::from_window(&windows[0]) };
+        assert_eq!(renamed.find(&windows), Some(1));
+        let windows = [windows.clone(), vec![window(3, "Other", "Chrome_WidgetWin_1", "chrome.exe")]].concat();
+        assert_eq!(renamed.find(&windows), None);
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Seconds of per-second rate kept for the chart.
+const HISTORY_SECS: usize = 120;
+
+/// Counters for one session, written by the click thread and reset on every
+/// start.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SessionStats {
+    /// Ticks: clicks, macro runs or script runs.
+    pub clicks: u64,
+    /// Input posts the backend refused, e.g. because the window went away.
+    pub failed: u64,
+    /// Ticks per second since the start.
+    pub measured_cps: f64,
+    /// Actual pause between two ticks minus the planned one, in ms. Only set
+    /// once two plain ticks followed each other.
+    pub interval_error: Option<IntervalError>,
+    /// Ticks in each whole second since the start, the last `HISTORY_SECS`
+    /// of them, oldest first, as (second, ticks).
+    pub cps_history: Vec<(u64, u64)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IntervalError {
+    pub min_ms: f64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+}
+
+pub struct StatsCollector {
+    started: Instant,
+    stats: SessionStats,
+    /// Previous tick, unless something else (a macro, a script) ran since.
+    previous: Option<Instant>,
+    error_sum: f64,
+    error_count: u64,
+    history: VecDeque<(u64, u64)>,
+}
+
+impl StatsCollector {
+    pub fn new() -> Self {
+        Self::start_at(Instant::now())
+    }
+
+    fn start_at(started: Instant) -> Self {
+        Self {
+            started,
+            stats: SessionStats::default(),
+            previous: None,
+            error_sum: 0.0,
+            error_count: 0,
+            history: VecDeque::with_capacity(HISTORY_SECS),
+        }
+    }
+
+    /// Counts a tick fired now; `planned` is the pause that was asked for
+    /// before it and `failed` the posts of this tick that did not go out.
+    pub fn tick(&mut self, planned: Duration, failed: u32) {
+        self.tick_at(Instant::now(), planned, failed);
+    }
+
+    fn tick_at(&mut self, now: Instant, planned: Duration, failed: u32) {
+        if let Some(previous) = self.previous {
+            let error = (now - previous).as_secs_f64() * 1000.0 - planned.as_secs_f64() * 1000.0;
+            self.error_sum += error;
+            self.error_count += 1;
+            let mean_ms = self.error_sum / self.error_count as f64;
+            self.stats.interval_error = Some(match self.stats.interval_error {
+                Some(e) => IntervalError { min_ms: e.min_ms.min(error), mean_ms, max_ms: e.max_ms.max(error) },
+                None => IntervalError { min_ms: error, mean_ms, max_ms: error },
+            });
+        }
+        self.previous = Some(now);
+
+        self.stats.clicks += 1;
+        self.stats.failed += u64::from(failed);
+
+        let second = (now - self.started).as_secs();
+        match self.history.back_mut() {
+            Some((last, ticks)) if *last == second => *ticks += 1,
+            _ => {
+                if self.history.len() == HISTORY_SECS {
+                    self.history.pop_front();
+                }
+                self.history.push_back((second, 1));
+            }
+        }
+    }
+
+    /// Leaves the next interval unmeasured, for ticks whose own work (a
+    /// macro, a script) is part of the pause.
+    pub fn interrupt(&mut self) {
+        self.previous = None;
+    }
+
+    pub fn snapshot(&self) -> SessionStats {
+        self.snapshot_at(Instant::now())
+    }
+
+    fn snapshot_at(&self, now: Instant) -> SessionStats {
+        let elapsed = (now - self.started).as_secs_f64();
+        SessionStats {
+            measured_cps: if elapsed > 0.0 { self.stats.clicks as f64 / elapsed } else { 0.0 },
+            cps_history: self.history.iter().copied().collect(),
+            ..self.stats.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn interval_error_is_signed() {
+        let start = Instant::now();
+        let mut stats = StatsCollector::start_at(start);
+        stats.tick_at(start, ms(100), 0);
+        stats.tick_at(start + ms(110), ms(100), 0);
+        stats.tick_at(start + ms(200), ms(100), 1);
+
+        let snapshot = stats.snapshot_at(start + ms(1000));
+        assert_eq!(snapshot.clicks, 3);
+        assert_eq!(snapshot.failed, 1);
+        assert!((snapshot.measured_cps - 3.0).abs() < 1e-9);
+        let error = snapshot.interval_error.unwrap();
+        assert!((error.min_ms + 10.0).abs() < 1e-6);
+        assert!((error.max_ms - 10.0).abs() < 1e-6);
+        assert!(error.mean_ms.abs() < 1e-6);
+    }
+
+    #[test]
+    fn interrupted_pause_is_not_counted() {
+        let start = Instant::now();
+        let mut stats = StatsCollector::start_at(start);
+        stats.tick_at(start, ms(100), 0);
+        stats.interrupt();
+        stats.tick_at(start + ms(900), ms(100), 0);
+        assert_eq!(stats.snapshot_at(start + ms(900)).interval_error, None);
+    }
+
+    #[test]
+    fn history_buckets_by_second() {
+        let start = Instant::now();
+        let mut stats = StatsCollector::start_at(start);
+        for tick in 0..5 {
+            stats.tick_at(start + ms(tick * 400), ms(400), 0);
+        }
+        assert_eq!(stats.snapshot_at(start + ms(2000)).cps_history, vec![(0, 3), (1, 2)]);
+    }
+}
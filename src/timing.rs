@@ -0,0 +1,238 @@
+use std::time::Duration;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rand_distr::{Distribution, Exp, Normal, weighted::WeightedIndex};
+use serde::{Deserialize, Serialize};
+
+/// Shortest pause between ticks; also the floor for every sampled interval.
+pub const MIN_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Longest pause between ticks; larger samples are cut down to it.
+pub const MAX_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// CPS targets accepted anywhere: the slider, the CLI and the API.
+pub const CPS_RANGE: std::ops::RangeInclusive<f64> = 0.5..=1000.0;
+
+/// Intervals in ms the slider and the API accept.
+pub const INTERVAL_RANGE: std::ops::RangeInclusive<u64> = 1..=2000;
+
+/// Width of a histogram bucket; samples land anywhere inside their bucket.
+pub const HISTOGRAM_BUCKET_MS: u64 = 10;
+
+/// How the pause between two clicks is chosen.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IntervalDistribution {
+    /// Always the base period: the slider interval or the CPS target.
+    #[default]
+    Fixed,
+    Uniform { min_ms: u64, max_ms: u64 },
+    Normal { mean_ms: f64, std_dev_ms: f64 },
+    /// Poisson arrivals: exponentially distributed gaps around the mean.
+    Exponential { mean_ms: f64 },
+    /// Replays the spread of a recorded run as (bucket start ms, count).
+    Histogram { buckets: Vec<(u64, u32)> },
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Timing {
+    pub distribution: IntervalDistribution,
+    /// Fixed seed for reproducible runs; a fresh one is drawn when unset.
+    pub seed: Option<u64>,
+}
+
+impl Timing {
+    /// Short human-readable form for the status bar.
+    pub fn describe(&self, interval_ms: u64, cps: Option<f64>) -> String {
+        let text = match &self.distribution {
+            IntervalDistribution::Fixed => match cps {
+                Some(cps) => format!("{} CPS", cps),
+                None => format!("{} ms", interval_ms),
+            },
+            IntervalDistribution::Uniform { min_ms, max_ms } => format!("{}–{} ms", min_ms, max_ms),
+            IntervalDistribution::Normal { mean_ms, std_dev_ms } => format!("N({:.0} ± {:.0}) ms", mean_ms, std_dev_ms),
+            IntervalDistribution::Exponential { mean_ms } => format!("Exp(~{:.0}) ms", mean_ms),
+            IntervalDistribution::Histogram { buckets } => {
+                let samples: u32 = buckets.iter().map(|(_, count)| count).sum();
+                format!("гистограмма ({} зн.)", samples)
+            }
+        };
+        match self.seed {
+            Some(seed) => format!("{}, seed {}", text, seed),
+            None => text,
+        }
+    }
+}
+
+/// Period the distributions are centred on. A CPS target, when set, wins
+/// over the millisecond interval so rates like 300 CPS are not rounded.
+pub fn base_period(interval_ms: u64, cps: Option<f64>) -> Duration {
+    let period = match cps {
+        // Clamped so a hand-edited settings file cannot ask for a period
+        // `Duration` cannot hold.
+        Some(cps) if cps > 0.0 => Duration::from_secs_f64(1.0 / cps.clamp(*CPS_RANGE.start(), *CPS_RANGE.end())),
+        _ => Duration::from_millis(interval_ms),
+    };
+    period.max(MIN_INTERVAL)
+}
+
+/// Buckets recorded intervals for `IntervalDistribution::Histogram`.
+pub fn histogram(intervals_ms: &[u64]) -> Vec<(u64, u32)> {
+    let mut buckets: Vec<(u64, u32)> = Vec::new();
+    for ms in intervals_ms {
+        let start = ms / HISTOGRAM_BUCKET_MS * HISTOGRAM_BUCKET_MS;
+        match buckets.iter_mut().find(|(bucket, _)| *bucket == start) {
+            Some((_, count)) => *count += 1,
+            None => buckets.push((start, 1)),
+        }
+    }
+    buckets.sort();
+    buckets
+}
+
+/// Draws intervals for one run. Two samplers built from the same seeded
+/// `Timing` produce the same sequence.
+pub struct IntervalSampler {
+    timing: Timing,
+    rng: StdRng,
+}
+
+impl IntervalSampler {
+    pub fn new(timing: Timing) -> Self {
+        let rng = match timing.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_os_rng(),
+        };
+        Self { timing, rng }
+    }
+
+    pub fn timing(&self) -> &Timing {
+        &self.timing
+    }
+
+    /// Next interval, between `MIN_INTERVAL` and `MAX_INTERVAL`. Parameters that make no
+    /// sense (a negative deviation, an empty histogram) fall back to `base`
+    /// or the mean rather than failing mid-run.
+    pub fn next(&mut self, base: Duration) -> Duration {
+        let base_ms = base.as_secs_f64() * 1000.0;
+        let ms = match &self.timing.distribution {
+            IntervalDistribution::Fixed => base_ms,
+            IntervalDistribution::Uniform { min_ms, max_ms } => {
+                let (low, high) = (*min_ms.min(max_ms), *min_ms.max(max_ms));
+                self.rng.random_range(low..=high) as f64
+            }
+            IntervalDistribution::Normal { mean_ms, std_dev_ms } => match Normal::new(*mean_ms, *std_dev_ms) {
+                Ok(normal) => normal.sample(&mut self.rng),
+                Err(_) => *mean_ms,
+            },
+            IntervalDistribution::Exponential { mean_ms } => match Exp::new(1.0 / mean_ms) {
+                Ok(exp) if *mean_ms > 0.0 => exp.sample(&mut self.rng),
+                _ => *mean_ms,
+            },
+            IntervalDistribution::Histogram { buckets } => {
+                match WeightedIndex::new(buckets.iter().map(|(_, count)| *count)) {
+                    Ok(index) => {
+                        let (start, _) = buckets[index.sample(&mut self.rng)];
+                        start.saturating_add(self.rng.random_range(0..HISTOGRAM_BUCKET_MS)) as f64
+                    }
+                    Err(_) => base_ms,
+                }
+            }
+        };
+        let ms = if ms.is_finite() { ms } else { base_ms };
+        let ms = ms.clamp(0.0, MAX_INTERVAL.as_secs_f64() * 1000.0);
+        Duration::from_secs_f64(ms / 1000.0).max(MIN_INTERVAL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sampler(distribution: IntervalDistribution, seed: u64) -> IntervalSampler {
+        IntervalSampler::new(Timing { distribution, seed: Some(seed) })
+    }
+
+    fn samples(sampler: &mut IntervalSampler, n: usize) -> Vec<u64> {
+        (0..n).map(|_| sampler.next(Duration::from_millis(500)).as_millis() as u64).collect()
+    }
+
+    #[test]
+    fn seeded_runs_repeat() {
+        let distribution = IntervalDistribution::Normal { mean_ms: 200.0, std_dev_ms: 40.0 };
+        let first = samples(&mut sampler(distribution.clone(), 7), 50);
+        assert_eq!(first, samples(&mut sampler(distribution.clone(), 7), 50));
+        assert_ne!(first, samples(&mut sampler(distribution, 8), 50));
+    }
+
+    #[test]
+    fn distributions_stay_in_range() {
+        let uniform = samples(&mut sampler(IntervalDistribution::Uniform { min_ms: 300, max_ms: 100 }, 1), 500);
+        assert!(uniform.iter().all(|ms| (100..=300).contains(ms)));
+        assert!(uniform.contains(&100) || uniform.contains(&300));
+
+        let normal = samples(&mut sampler(IntervalDistribution::Normal { mean_ms: 5.0, std_dev_ms: 50.0 }, 2), 500);
+        assert!(normal.iter().all(|ms| *ms >= 1));
+
+        let exp = samples(&mut sampler(IntervalDistribution::Exponential { mean_ms: 100.0 }, 3), 5000);
+        let mean = exp.iter().sum::<u64>() as f64 / exp.len() as f64;
+        assert!((85.0..115.0).contains(&mean), "{}", mean);
+
+        let fixed = samples(&mut sampler(IntervalDistribution::Fixed, 4), 3);
+        assert_eq!(fixed, vec![500, 500, 500]);
+    }
+
+    #[test]
+    fn histogram_replays_recorded_buckets() {
+        let buckets = histogram(&[101, 104, 109, 250, 252]);
+        assert_eq!(buckets, vec![(100, 3), (250, 2)]);
+
+        let drawn = samples(&mut sampler(IntervalDistribution::Histogram { buckets }, 5), 200);
+        assert!(drawn.iter().all(|ms| (100..110).contains(ms) || (250..260).contains(ms)));
+        assert!(drawn.iter().any(|ms| *ms >= 250));
+
+        let empty = samples(&mut sampler(IntervalDistribution::Histogram { buckets: vec![] }, 6), 1);
+        assert_eq!(empty, vec![500]);
+    }
+
+    #[test]
+    fn absurd_parameters_are_cut_to_the_longest_pause() {
+        let absurd = [
+            IntervalDistribution::Normal { mean_ms: 1e300, std_dev_ms: 1e300 },
+            IntervalDistribution::Normal { mean_ms: f64::MAX, std_dev_ms: 0.0 },
+            IntervalDistribution::Exponential { mean_ms: 1e300 },
+            IntervalDistribution::Uniform { min_ms: u64::MAX - 1, max_ms: u64::MAX },
+            IntervalDistribution::Histogram { buckets: vec![(u64::MAX, 1)] },
+        ];
+        for distribution in absurd {
+            let mut sampler = sampler(distribution.clone(), 5);
+            for _ in 0..20 {
+                let pause = sampler.next(Duration::from_millis(500));
+                assert!((MIN_INTERVAL..=MAX_INTERVAL).contains(&pause), "{:?}: {:?}", distribution, pause);
+            }
+        }
+    }
+
+    #[test]
+    fn describes_distribution_and_seed() {
+        let timing = Timing {
+            distribution: IntervalDistribution::Uniform { min_ms: 80, max_ms: 120 },
+            seed: Some(42),
+        };
+        assert_eq!(timing.describe(500, None), "80–120 ms, seed 42");
+        assert_eq!(Timing::default().describe(500, None), "500 ms");
+        assert_eq!(Timing::default().describe(500, Some(300.0)), "300 CPS");
+    }
+
+    #[test]
+    fn cps_target_overrides_interval() {
+        assert_eq!(base_period(500, None), Duration::from_millis(500));
+        assert_eq!(base_period(500, Some(4.0)), Duration::from_millis(250));
+        assert_eq!(base_period(0, None), MIN_INTERVAL);
+        assert_eq!(base_period(500, Some(5000.0)), MIN_INTERVAL);
+        assert_eq!(base_period(500, Some(1e-300)), Duration::from_secs(2));
+        assert_eq!(base_period(500, Some(f64::INFINITY)), MIN_INTERVAL);
+        let mut fixed = IntervalSampler::new(Timing::default());
+        assert_eq!(fixed.next(base_period(500, Some(300.0))).as_micros(), 3333);
+    }
+}
@@ -0,0 +1,99 @@
+use eframe::egui;
+use egui::{Color32, RichText};
+
+use crate::api::{self, ApiSettings};
+
+/// Edits a copy of the API settings; they only take effect, and restart the
+/// server, once applied, so a half-typed socket path is never bound.
+pub struct ApiPanel {
+    draft: ApiSettings,
+}
+
+impl ApiPanel {
+    pub fn new(settings: ApiSettings) -> Self {
+        Self { draft: settings }
+    }
+
+    /// `applied` are the settings in use and `status` the listening address
+    /// or why it failed. Returns the edited settings when the user applies
+    /// them; the app then restarts the server.
+    pub fn render(
+        &mut self,
+        ui: &mut egui::Ui,
+        applied: &ApiSettings,
+        status: Option<Result<&str, &str>>,
+    ) -> Option<ApiSettings> {
+        let mut apply = None;
+        let settings = &mut self.draft;
+        egui::CollapsingHeader::new(RichText::new("🔌 API управления").strong())
+            .default_open(applied.enabled)
+            .show(ui, |ui| {
+                if ui.checkbox(&mut settings.enabled, "Включить локальный HTTP API").changed()
+                    && settings.enabled
+                    && settings.token.is_empty()
+                {
+                    settings.token = api::new_token();
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("Порт:");
+                    ui.add(egui::DragValue::new(&mut settings.port).range(1024..=65535));
+                });
+
+                #[cfg(unix)]
+                ui.horizontal(|ui| {
+                    let mut use_socket = settings.unix_socket.is_some();
+                    if ui.checkbox(&mut use_socket, "Unix-сокет:").changed() {
+                        settings.unix_socket = use_socket.then(|| "/tmp/beclicker.sock".to_string());
+                    }
+                    if let Some(path) = &mut settings.unix_socket {
+                        ui.add(egui::TextEdit::singleline(path).desired_width(160.0));
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    ui.label("Токен:");
+                    ui.label(RichText::new(&settings.token).monospace().small());
+                    if ui.small_button("📋").on_hover_text("Копировать").clicked() {
+                        ui.ctx().copy_text(settings.token.clone());
+                    }
+                    if ui.small_button("🔄").on_hover_text("Новый токен").clicked() {
+                        settings.token = api::new_token();
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    let changed = settings != applied;
+                    // Unchanged settings can still be re-applied to retry a failed start.
+                    let failed = matches!(status, Some(Err(_)));
+                    if ui.add_enabled(changed || failed, egui::Button::new("✔ Применить")).clicked() {
+                        apply = Some(settings.clone());
+                    }
+                    if changed && ui.button("↩ Отменить").clicked() {
+                        *settings = applied.clone();
+                    }
+                });
+
+                match status {
+                    Some(Ok(address)) => {
+                        ui.label(RichText::new(format!("Слушает {}", address))
+                            .color(Color32::from_rgb(0, 180, 100))
+                            .small());
+                    }
+                    Some(Err(error)) => {
+                        ui.label(RichText::new(format!("⚠ Не запущен: {}", error))
+                            .color(Color32::from_rgb(220, 160, 60))
+                            .small());
+                    }
+                    None => {}
+                }
+                ui.label(RichText::new(
+                    "GET /status, /windows · POST /start, /stop · PUT /interval, /target · ?job=имя; \
+                     заголовок Authorization: Bearer <токен>",
+                )
+                .color(Color32::from_rgb(150, 150, 170))
+                .small());
+            });
+        apply
+    }
+}
@@ -0,0 +1,329 @@
+use std::sync::atomic::Ordering;
+use eframe::egui;
+use egui::{Color32, RichText};
+
+use crate::hotkey_manager::HotkeyManager;
+use crate::job::Job;
+use crate::settings::{Profile, WindowTarget};
+use crate::window_filter::{Query, WindowFilter, WindowSort};
+use crate::window_manager::WindowInfo;
+
+/// Windows remembered for the "recently used" order.
+const RECENT_WINDOWS: usize = 20;
+
+/// What the user asked for this frame; the app applies it so the jobs and
+/// the stored profiles stay in step. Targets and start/stop are written to
+/// the job directly.
+pub enum JobAction {
+    Select(usize),
+    Add(String),
+    Remove(usize),
+    Rename(usize, String),
+    ClearHotkey,
+}
+
+pub struct JobsPanel {
+    pub listening_for_key: bool,
+    /// Shared by all window pickers; the app saves it with the settings.
+    pub window_filter: WindowFilter,
+    new_name: String,
+    renaming: Option<usize>,
+    search: String,
+    /// `search` prepared for the `regex` setting it was built with; rebuilt
+    /// only when either changes, not every frame.
+    query: (String, bool, Result<Query, String>),
+    /// Picked windows, most recent first; the app saves it with the settings.
+    pub recent: Vec<WindowTarget>,
+}
+
+impl JobsPanel {
+    pub fn new(window_filter: WindowFilter, recent: Vec<WindowTarget>) -> Self {
+        Self {
+            listening_for_key: false,
+            window_filter,
+            new_name: String::new(),
+            renaming: None,
+            search: String::new(),
+            query: (String::new(), false, Ok(Query::Any)),
+            recent,
+        }
+    }
+
+    /// `profiles` and `jobs` are parallel: job `i` runs profile `i`.
+    pub fn render(
+        &mut self,
+        ui: &mut egui::Ui,
+        profiles: &[Profile],
+        jobs: &[Job],
+        windows: &[WindowInfo],
+        selected: usize,
+        select_hotkey_vk: u32,
+    ) -> Option<JobAction> {
+        let mut action = None;
+
+        ui.heading(RichText::new("📋 Задания").color(Color32::from_rgb(120, 180, 255)));
+        ui.separator();
+
+        egui::Grid::new("jobs").num_columns(5).spacing([6.0, 4.0]).show(ui, |ui| {
+            for (index, (profile, job)) in profiles.iter().zip(jobs).enumerate() {
+                let running = job.is_running();
+                let dot_color = if running {
+                    Color32::from_rgb(0, 200, 100)
+                } else {
+                    Color32::from_rgb(200, 80, 80)
+                };
+                ui.label(RichText::new("●").color(dot_color));
+
+                if self.renaming == Some(index) {
+                    let response = ui.add(egui::TextEdit::singleline(&mut self.new_name).desired_width(100.0));
+                    if response.lost_focus() {
+                        // Schedules find their job by name, so names stay unique.
+                        let name = self.new_name.trim();
+                        let taken = profiles.iter().enumerate().any(|(other, p)| other != index && p.name == name);
+                        if !name.is_empty() && !taken {
+                            action = Some(JobAction::Rename(index, name.to_string()));
+                        }
+                        self.renaming = None;
+                        self.new_name.clear();
+                    } else {
+                        response.request_focus();
+                    }
+                } else {
+                    let is_selected = index == selected;
+                    let response = ui.selectable_label(
+                        is_selected,
+                        RichText::new(&profile.name).color(if is_selected {
+                            Color32::WHITE
+                        } else {
+                            Color32::from_rgb(200, 200, 220)
+                        }),
+                    ).on_hover_text("Настроить");
+                    if response.clicked() && !is_selected {
+                        action = Some(JobAction::Select(index));
+                    }
+                    if response.double_clicked() {
+                        self.renaming = Some(index);
+                        self.new_name = profile.name.clone();
+                    }
+                }
+
+                let hwnd = *job.controls.selected_hwnd.read();
+                let selected_window = hwnd.and_then(|hwnd| windows.iter().find(|window| window.hwnd == hwnd));
+                let title = selected_window
+                    .map(|window| window.title.as_str())
+                    .or(profile.target.as_ref().map(|target| target.title.as_str()))
+                    .unwrap_or("— окно —");
+                let combo = egui::ComboBox::from_id_salt(("job_window", index))
+                    .selected_text(truncate(title, 24))
+                    .width(160.0)
+                    // The search field and toggles take clicks; a pick closes it.
+                    .close_behavior(egui::PopupCloseBehavior::CloseOnClickOutside)
+                    .show_ui(ui, |ui| {
+                        if let Some(hwnd) = self.window_picker(ui, windows, hwnd) {
+                            ui.close();
+                            *job.controls.selected_hwnd.write() = Some(hwnd);
+                            if let Some(window) = windows.iter().find(|window| window.hwnd == hwnd) {
+                                let target = WindowTarget::from_window(window);
+                                self.recent.retain(|recent| *recent != target);
+                                self.recent.insert(0, target);
+                                self.recent.truncate(RECENT_WINDOWS);
+                            }
+                        }
+                    });
+                if let Some(window) = selected_window {
+                    combo.response.on_hover_text(window_details(window));
+                }
+
+                let hotkey = match *job.hotkey_vk.read() {
+                    0 => "—".to_string(),
+                    vk => HotkeyManager::vk_to_key_name(vk),
+                };
+                ui.label(RichText::new(hotkey).color(Color32::from_rgb(180, 180, 200)));
+
+                ui.horizontal(|ui| {
+                    let (text, color) = if running {
+                        ("⏹", Color32::from_rgb(200, 80, 80))
+                    } else {
+                        ("▶", Color32::from_rgb(0, 180, 100))
+                    };
+                    if ui.add_enabled(running || hwnd.is_some(),
+                        egui::Button::new(RichText::new(text).color(Color32::WHITE)).fill(color))
+                        .on_disabled_hover_text("Сначала выберите окно")
+                        .clicked()
+                    {
+                        job.controls.running.store(!running, Ordering::SeqCst);
+                    }
+                    if ui.small_button("✏").on_hover_text("Переименовать").clicked() {
+                        self.renaming = Some(index);
+                        self.new_name = profile.name.clone();
+                    }
+                    if profiles.len() > 1
+                        && ui.small_button("🗑").on_hover_text("Удалить").clicked()
+                    {
+                        action = Some(JobAction::Remove(index));
+                    }
+                });
+                ui.end_row();
+            }
+        });
+
+        ui.add_space(5.0);
+        ui.horizontal(|ui| {
+            if ui.add(egui::Button::new(RichText::new("➕ Копия выбранного").color(Color32::WHITE))
+                .fill(Color32::from_rgb(70, 100, 180)))
+                .clicked()
+            {
+                action = Some(JobAction::Add(free_name(profiles)));
+            }
+            if jobs.iter().any(Job::is_running) && ui.button("⏹ Остановить все").clicked() {
+                for job in jobs {
+                    job.controls.running.store(false, Ordering::SeqCst);
+                }
+            }
+        });
+
+        ui.add_space(10.0);
+        ui.separator();
+        ui.add_space(10.0);
+
+        ui.label(RichText::new("Клавиша выбора следующего задания:").strong());
+        ui.horizontal(|ui| {
+            let current_key = if select_hotkey_vk == 0 {
+                "Не задана".to_string()
+            } else {
+                HotkeyManager::vk_to_key_name(select_hotkey_vk)
+            };
+            let (button_text, button_color) = if self.listening_for_key {
+                ("Нажмите любую клавишу...", Color32::from_rgb(200, 150, 50))
+            } else {
+                (current_key.as_str(), Color32::from_rgb(70, 100, 180))
+            };
+
+            if ui.add(egui::Button::new(RichText::new(button_text).color(Color32::WHITE))
+                .fill(button_color)
+                .min_size(egui::vec2(150.0, 35.0)))
+                .clicked()
+            {
+                self.listening_for_key = true;
+            }
+
+            let reset_button = ui.button("❌");
+            if reset_button.clicked() {
+                self.listening_for_key = false;
+                action = Some(JobAction::ClearHotkey);
+            }
+            reset_button.on_hover_text("Отключить");
+        });
+
+        action
+    }
+
+    /// Search, sort and hide options above the filtered window list; returns
+    /// the window clicked.
+    fn window_picker(&mut self, ui: &mut egui::Ui, windows: &[WindowInfo], hwnd: Option<isize>) -> Option<isize> {
+        let filter = &mut self.window_filter;
+        ui.set_min_width(300.0);
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.search)
+                .hint_text("🔍 Название, класс или процесс")
+                .desired_width(240.0));
+            ui.toggle_value(&mut filter.regex, ".*").on_hover_text("Регулярное выражение");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Порядок:");
+            ui.selectable_value(&mut filter.sort, WindowSort::System, "системный");
+            ui.selectable_value(&mut filter.sort, WindowSort::Title, "по названию");
+            ui.selectable_value(&mut filter.sort, WindowSort::Process, "по процессу");
+            ui.selectable_value(&mut filter.sort, WindowSort::Recent, "недавние");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Скрыть:");
+            ui.checkbox(&mut filter.hide_shell, "системные")
+                .on_hover_text("Рабочий стол, панель задач (Program Manager и т.п.)");
+            ui.checkbox(&mut filter.hide_tool, "служебные");
+            ui.checkbox(&mut filter.hide_empty, "нулевого размера");
+        });
+        ui.separator();
+
+        if (self.query.0.as_str(), self.query.1) != (self.search.as_str(), filter.regex) {
+            self.query = (self.search.clone(), filter.regex, Query::new(&self.search, filter.regex));
+        }
+
+        let mut picked = None;
+        match self.query.2.as_ref().map(|query| filter.apply(query, windows, &self.recent)) {
+            Ok(list) if list.is_empty() => {
+                ui.label(RichText::new("Нет подходящих окон").weak());
+            }
+            Ok(list) => {
+                egui::ScrollArea::vertical().max_height(250.0).show(ui, |ui| {
+                    for window in list {
+                        let text = if window.minimized {
+                            format!("{} (свернуто)", window.label())
+                        } else {
+                            window.label()
+                        };
+                        if ui.selectable_label(hwnd == Some(window.hwnd), text)
+                            .on_hover_text(window_details(window))
+                            .clicked()
+                        {
+                            picked = Some(window.hwnd);
+                        }
+                    }
+                });
+            }
+            Err(error) => {
+                ui.label(RichText::new(error).color(Color32::from_rgb(220, 100, 100)).monospace());
+            }
+        }
+        picked
+    }
+}
+
+// Everything known about a window, for tooltips.
+fn window_details(window: &WindowInfo) -> String {
+    let mut lines = vec![window.title.clone()];
+    if let Some(process) = &window.process {
+        lines.push(match window.pid {
+            Some(pid) => format!("Процесс: {} (PID {})", process, pid),
+            None => format!("Процесс: {}", process),
+        });
+    } else if let Some(pid) = window.pid {
+        lines.push(format!("PID: {}", pid));
+    }
+    if let Some(path) = &window.exe_path {
+        lines.push(format!("Путь: {}", path.display()));
+    }
+    if let Some(class) = &window.class {
+        lines.push(format!("Класс: {}", class));
+    }
+    if let Some((width, height)) = window.client_size {
+        lines.push(format!("Клиентская область: {}×{}", width, height));
+    }
+    if window.tool {
+        lines.push("Служебное окно".to_string());
+    }
+    match (window.minimized, window.visible) {
+        (true, _) => lines.push("Свернуто".to_string()),
+        (false, false) => lines.push("Не показано".to_string()),
+        (false, true) => {}
+    }
+    if let Some(owner) = window.owner {
+        lines.push(format!("Владелец: {:#X}", owner));
+    }
+    lines.join("\n")
+}
+
+// Schedules and the API find jobs by name, so a new one must not reuse one.
+fn free_name(profiles: &[Profile]) -> String {
+    (profiles.len() + 1..)
+        .map(|number| format!("Задание {}", number))
+        .find(|name| profiles.iter().all(|profile| profile.name != *name))
+        .expect("some number is free")
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
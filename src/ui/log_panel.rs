@@ -0,0 +1,58 @@
+use eframe::egui;
+use egui::{Color32, RichText};
+
+use crate::logging::{LogLevel, Logging};
+
+pub struct LogPanel;
+
+impl LogPanel {
+    /// Returns the new level when the user picked one.
+    pub fn render(&self, ui: &mut egui::Ui, logging: &Logging, level: LogLevel) -> Option<LogLevel> {
+        let mut picked = None;
+
+        egui::CollapsingHeader::new(RichText::new("🗒 Журнал").strong())
+            .default_open(false)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Уровень:");
+                    egui::ComboBox::from_id_salt("log_level")
+                        .selected_text(level.name())
+                        .show_ui(ui, |ui| {
+                            for option in LogLevel::ALL {
+                                if ui.selectable_label(option == level, option.name()).clicked() && option != level {
+                                    picked = Some(option);
+                                }
+                            }
+                        });
+                });
+
+                let lines = logging.tail().lines();
+                egui::ScrollArea::vertical()
+                    .max_height(160.0)
+                    .stick_to_bottom(true)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        for line in &lines {
+                            let color = if line.contains("ERROR") {
+                                Color32::from_rgb(220, 80, 80)
+                            } else if line.contains("WARN") {
+                                Color32::from_rgb(220, 160, 60)
+                            } else {
+                                Color32::from_rgb(180, 180, 200)
+                            };
+                            ui.label(RichText::new(line).color(color).monospace().small());
+                        }
+                    });
+
+                let location = match &logging.dir {
+                    Some(dir) => format!("Файлы: {}", dir.display()),
+                    None => "Файл журнала недоступен".to_string(),
+                };
+                ui.label(RichText::new(location)
+                    .color(Color32::from_rgb(150, 150, 170))
+                    .small());
+            });
+
+        picked
+    }
+}
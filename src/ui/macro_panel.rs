@@ -0,0 +1,174 @@
+use eframe::egui;
+use egui::{Color32, RichText};
+
+use crate::hotkey_manager::HotkeyManager;
+use crate::macros::Macro;
+
+pub enum MacroAction {
+    ToggleRecording,
+    SetPlayback(bool),
+    Clear,
+    ClearHotkey,
+    /// Replace the interval distribution with the recorded press timings.
+    UseTimings,
+    Save(String),
+    Load(String),
+}
+
+pub struct MacroPanel {
+    pub listening_for_key: bool,
+    /// Result of the last save or load, shown under the file controls.
+    pub message: Option<String>,
+    file_name: String,
+    saved: Vec<String>,
+}
+
+impl MacroPanel {
+    pub fn new() -> Self {
+        Self {
+            listening_for_key: false,
+            message: None,
+            file_name: String::new(),
+            saved: Macro::list_saved(),
+        }
+    }
+
+    pub fn refresh_saved(&mut self) {
+        self.saved = Macro::list_saved();
+    }
+
+    /// `recording` is the number of steps captured so far while a recording
+    /// is in progress.
+    pub fn render(
+        &mut self,
+        ui: &mut egui::Ui,
+        recording: Option<usize>,
+        recorded: Option<&Macro>,
+        playing: bool,
+        hotkey_vk: u32,
+        can_record: bool,
+    ) -> Option<MacroAction> {
+        let mut action = None;
+
+        ui.heading(RichText::new("⏺ Макрос").color(Color32::from_rgb(120, 180, 255)));
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            let (record_text, record_color) = match recording {
+                Some(_) => ("⏹ Остановить", Color32::from_rgb(200, 80, 80)),
+                None => ("⏺ Записать", Color32::from_rgb(70, 100, 180)),
+            };
+            if ui.add_enabled(recording.is_some() || can_record,
+                egui::Button::new(RichText::new(record_text).color(Color32::WHITE))
+                    .fill(record_color)
+                    .min_size(egui::vec2(120.0, 30.0)))
+                .on_disabled_hover_text("Сначала выберите окно")
+                .clicked()
+            {
+                action = Some(MacroAction::ToggleRecording);
+            }
+
+            if recorded.is_some()
+                && recording.is_none()
+                && ui.button("🗑").on_hover_text("Удалить макрос").clicked()
+            {
+                action = Some(MacroAction::Clear);
+            }
+        });
+
+        if let Some(steps) = recording {
+            ui.label(RichText::new(format!("Идет запись... шагов: {}", steps))
+                .color(Color32::from_rgb(220, 160, 60)));
+        } else if let Some(recorded) = recorded {
+            ui.label(RichText::new(format!(
+                "Шагов: {}, длительность: {:.1} с",
+                recorded.steps.len(),
+                recorded.duration().as_secs_f32()
+            ))
+            .color(Color32::from_rgb(180, 180, 200)));
+
+            let mut play = playing;
+            if ui.checkbox(&mut play, "Воспроизводить вместо клика").changed() {
+                action = Some(MacroAction::SetPlayback(play));
+            }
+            if ui.add_enabled(recorded.press_intervals().len() >= 2, egui::Button::new("📊 Тайминги в интервал"))
+                .on_hover_text("Интервалы между кликами станут гистограммой разброса")
+                .on_disabled_hover_text("Нужно хотя бы три клика")
+                .clicked()
+            {
+                action = Some(MacroAction::UseTimings);
+            }
+        } else {
+            ui.label(RichText::new("Нет записанного макроса")
+                .color(Color32::from_rgb(150, 150, 170))
+                .small());
+        }
+
+        ui.add_space(10.0);
+        ui.label(RichText::new("Файлы:").strong());
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.file_name)
+                .hint_text("имя")
+                .desired_width(120.0));
+            let name = self.file_name.trim();
+            let can_save = recorded.is_some()
+                && !name.is_empty()
+                && !name.contains(['/', '\\', '.']);
+            if ui.add_enabled(can_save, egui::Button::new("💾 Сохранить")).clicked() {
+                action = Some(MacroAction::Save(name.to_string()));
+            }
+        });
+        if !self.saved.is_empty() {
+            ui.horizontal_wrapped(|ui| {
+                for name in &self.saved {
+                    if ui.small_button(format!("📂 {}", name)).on_hover_text("Загрузить").clicked() {
+                        self.file_name = name.clone();
+                        action = Some(MacroAction::Load(name.clone()));
+                    }
+                }
+            });
+        }
+        if let Some(message) = &self.message {
+            ui.label(RichText::new(message)
+                .color(Color32::from_rgb(220, 160, 60))
+                .small());
+        }
+
+        ui.add_space(10.0);
+        ui.label(RichText::new("Клавиша записи:").strong());
+        ui.horizontal(|ui| {
+            let current_key = if hotkey_vk == 0 {
+                "Не задана".to_string()
+            } else {
+                HotkeyManager::vk_to_key_name(hotkey_vk)
+            };
+            let (button_text, button_color) = if self.listening_for_key {
+                ("Нажмите любую клавишу...", Color32::from_rgb(200, 150, 50))
+            } else {
+                (current_key.as_str(), Color32::from_rgb(70, 100, 180))
+            };
+
+            if ui.add(egui::Button::new(RichText::new(button_text).color(Color32::WHITE))
+                .fill(button_color)
+                .min_size(egui::vec2(150.0, 35.0)))
+                .clicked()
+            {
+                self.listening_for_key = true;
+            }
+
+            let reset_button = ui.button("❌");
+            if reset_button.clicked() {
+                self.listening_for_key = false;
+                action = Some(MacroAction::ClearHotkey);
+            }
+            reset_button.on_hover_text("Отключить");
+        });
+
+        ui.add_space(5.0);
+        ui.label(RichText::new("Клики записываются в координатах выбранного окна")
+            .color(Color32::from_rgb(150, 150, 170))
+            .small());
+
+        action
+    }
+}
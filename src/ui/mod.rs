@@ -1,3 +1,8 @@
 pub mod top_panel;
-pub mod windows_list;
-pub mod settings_panel;
\ No newline at end of file
+pub mod settings_panel;
+pub mod jobs_panel;
+pub mod macro_panel;
+pub mod script_panel;
+pub mod schedule_panel;
+pub mod log_panel;
+pub mod api_panel;
\ No newline at end of file
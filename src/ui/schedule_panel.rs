@@ -0,0 +1,179 @@
+use chrono::{NaiveDateTime, NaiveTime, TimeDelta, Weekday};
+use eframe::egui;
+use egui::{Color32, RichText};
+
+use crate::schedule::{MissedRun, ScheduleEntry, Trigger, weekday_name};
+use crate::settings::{Profile, WindowTarget};
+use crate::window_manager::WindowInfo;
+
+const WEEK: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+pub enum ScheduleAction {
+    Add(ScheduleEntry),
+    Remove(usize),
+    SetEnabled(usize, bool),
+}
+
+pub struct SchedulePanel {
+    /// Last start or missed run, shown under the list.
+    pub message: Option<String>,
+    profile: usize,
+    hour: u32,
+    minute: u32,
+    recurring: bool,
+    days: Vec<Weekday>,
+    run_for_minutes: Option<u64>,
+    use_current_window: bool,
+    run_late: bool,
+}
+
+impl SchedulePanel {
+    pub fn new() -> Self {
+        Self {
+            message: None,
+            profile: 0,
+            hour: 9,
+            minute: 0,
+            recurring: true,
+            days: WEEK[..5].to_vec(),
+            run_for_minutes: Some(20),
+            use_current_window: false,
+            run_late: false,
+        }
+    }
+
+    /// `current_window` is the selected window, offered as the target of a
+    /// new entry.
+    pub fn render(
+        &mut self,
+        ui: &mut egui::Ui,
+        entries: &[ScheduleEntry],
+        profiles: &[Profile],
+        current_window: Option<&WindowInfo>,
+        now: NaiveDateTime,
+    ) -> Option<ScheduleAction> {
+        let mut action = None;
+
+        ui.heading(RichText::new("⏰ Расписание").color(Color32::from_rgb(120, 180, 255)));
+        ui.separator();
+
+        for (index, entry) in entries.iter().enumerate() {
+            ui.horizontal(|ui| {
+                let mut enabled = entry.enabled;
+                if ui.checkbox(&mut enabled, "").changed() {
+                    action = Some(ScheduleAction::SetEnabled(index, enabled));
+                }
+                let mut text = format!("{} → {}", entry.trigger.describe(), entry.profile);
+                if let Some(secs) = entry.run_for_secs {
+                    text += &format!(", {} мин", secs / 60);
+                }
+                if let Some(target) = &entry.target {
+                    text += &format!(" [{}]", target.title);
+                }
+                ui.label(text);
+                if ui.small_button("🗑").on_hover_text("Удалить").clicked() {
+                    action = Some(ScheduleAction::Remove(index));
+                }
+            });
+            let next = match entry.trigger.next(now) {
+                Some(next) if entry.enabled => format!("Следующий запуск: {}", next.format("%d.%m %H:%M")),
+                Some(_) => "Отключено".to_string(),
+                None => "Выполнено".to_string(),
+            };
+            ui.label(RichText::new(next).color(Color32::from_rgb(150, 150, 170)).small());
+        }
+        if entries.is_empty() {
+            ui.label(RichText::new("Нет запланированных запусков")
+                .color(Color32::from_rgb(150, 150, 170))
+                .small());
+        }
+
+        ui.add_space(10.0);
+        ui.label(RichText::new("Новый запуск:").strong());
+        self.profile = self.profile.min(profiles.len().saturating_sub(1));
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("schedule_profile")
+                .selected_text(profiles.get(self.profile).map_or("", |profile| profile.name.as_str()))
+                .show_ui(ui, |ui| {
+                    for (index, profile) in profiles.iter().enumerate() {
+                        ui.selectable_value(&mut self.profile, index, &profile.name);
+                    }
+                });
+            ui.label("в");
+            ui.add(egui::DragValue::new(&mut self.hour).range(0..=23));
+            ui.add(egui::DragValue::new(&mut self.minute).range(0..=59).custom_formatter(|m, _| format!("{:02}", m)));
+        });
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.recurring, false, "Один раз");
+            ui.selectable_value(&mut self.recurring, true, "По дням");
+        });
+        if self.recurring {
+            ui.horizontal(|ui| {
+                for day in WEEK {
+                    let mut on = self.days.contains(&day);
+                    if ui.toggle_value(&mut on, weekday_name(day)).changed() {
+                        self.days.retain(|d| *d != day);
+                        if on {
+                            self.days.push(day);
+                            self.days.sort_by_key(|d| d.num_days_from_monday());
+                        }
+                    }
+                }
+            });
+        }
+        ui.horizontal(|ui| {
+            let mut limited = self.run_for_minutes.is_some();
+            if ui.checkbox(&mut limited, "Длительность:").changed() {
+                self.run_for_minutes = limited.then_some(20);
+            }
+            if let Some(minutes) = &mut self.run_for_minutes {
+                ui.add(egui::DragValue::new(minutes).range(1..=24 * 60).suffix(" мин"));
+            }
+        });
+        ui.add_enabled(current_window.is_some(),
+            egui::Checkbox::new(&mut self.use_current_window, "В выбранное окно вместо окна задания"));
+        ui.checkbox(&mut self.run_late, "Запустить с опозданием, если пропущено")
+            .on_hover_text("Иначе запуск, пропущенный во время сна или пока программа была закрыта, отменяется");
+
+        let can_add = !profiles.is_empty() && (!self.recurring || !self.days.is_empty());
+        if ui.add_enabled(can_add, egui::Button::new("➕ Добавить")).clicked() {
+            let time = NaiveTime::from_hms_opt(self.hour, self.minute, 0).unwrap_or_default();
+            let trigger = if self.recurring {
+                // All seven days is stored as "every day".
+                let days = if self.days.len() == WEEK.len() { Vec::new() } else { self.days.clone() };
+                Trigger::Weekly { time, days }
+            } else {
+                let mut at = now.date().and_time(time);
+                if at <= now {
+                    at += TimeDelta::days(1);
+                }
+                Trigger::Once { at }
+            };
+            action = Some(ScheduleAction::Add(ScheduleEntry {
+                enabled: true,
+                profile: profiles[self.profile].name.clone(),
+                target: current_window.filter(|_| self.use_current_window).map(WindowTarget::from_window),
+                trigger,
+                run_for_secs: self.run_for_minutes.map(|minutes| minutes * 60),
+                missed: if self.run_late { MissedRun::RunLate } else { MissedRun::Skip },
+                last_fired: Some(now),
+            }));
+        }
+
+        if let Some(message) = &self.message {
+            ui.label(RichText::new(message)
+                .color(Color32::from_rgb(220, 160, 60))
+                .small());
+        }
+
+        action
+    }
+}
@@ -0,0 +1,39 @@
+use eframe::egui;
+use egui::{Color32, RichText};
+
+pub struct ScriptPanel;
+
+impl ScriptPanel {
+    /// Returns true when the script text or the toggle changed this frame.
+    pub fn render(&self, ui: &mut egui::Ui, source: &mut String, enabled: &mut bool, error: Option<&str>) -> bool {
+        let mut changed = false;
+
+        egui::CollapsingHeader::new(RichText::new("📜 Скрипт (Rhai)").strong())
+            .default_open(*enabled)
+            .show(ui, |ui| {
+                changed |= ui.checkbox(enabled, "Выполнять скрипт вместо клика").changed();
+
+                changed |= ui.add(egui::TextEdit::multiline(source)
+                    .code_editor()
+                    .desired_rows(8)
+                    .desired_width(f32::INFINITY)
+                    .hint_text("click(); sleep(100); key(\"space\");"))
+                    .changed();
+
+                if let Some(error) = error {
+                    ui.label(RichText::new(format!("⚠ {}", error))
+                        .color(Color32::from_rgb(220, 160, 60))
+                        .small());
+                }
+
+                ui.label(RichText::new(
+                    "click(), click(x, y), click(\"right\", x, y), key(\"a\"), key_down/key_up, sleep(ms), \
+                     window_title(), is_running(), pixel_at(x, y)",
+                )
+                .color(Color32::from_rgb(150, 150, 170))
+                .small());
+            });
+
+        changed
+    }
+}
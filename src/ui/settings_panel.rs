@@ -1,22 +1,40 @@
 use eframe::egui;
+use chrono::Timelike;
 use egui::{Color32, RichText};
-use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use egui_plot::{Bar, BarChart, Plot};
+use std::sync::{Arc, atomic::Ordering};
 use parking_lot::RwLock;
 
+use crate::backend::MouseButton;
+use crate::clicker::{ClickCount, ClickerControls};
 use crate::hotkey_manager::HotkeyManager;
+use crate::session::{SessionStatus, StopReason, StopRules};
+use crate::stats::SessionStats;
+use crate::timing::{self, IntervalDistribution, Timing};
 
-pub struct SettingsPanel;
+pub struct SettingsPanel {
+    pub listening_for_key: bool,
+}
 
 impl SettingsPanel {
+    pub fn new() -> Self {
+        Self {
+            listening_for_key: false,
+        }
+    }
+
+    /// Returns true when the user asked to restart the job's threads after
+    /// its input backend failed.
     pub fn render(
-        &self,
+        &mut self,
         ui: &mut egui::Ui,
         hotkey_vk: &Arc<RwLock<u32>>,
-        interval_ms: &Arc<RwLock<u64>>,
-        running: &Arc<AtomicBool>,
+        controls: &ClickerControls,
+        backend_error: Option<&str>,
         animation_progress: f32,
-        listening_for_key: &mut bool,
-    ) {
+    ) -> bool {
+        let mut restart = false;
+
         ui.heading(RichText::new("⚙ Настройки").color(Color32::from_rgb(120, 180, 255)));
         ui.separator();
 
@@ -24,14 +42,17 @@ impl SettingsPanel {
             ui.label(RichText::new("Горячая клавиша:").strong());
 
             ui.horizontal(|ui| {
-                let current_key = HotkeyManager::vk_to_key_name(*hotkey_vk.read());
-                let button_text = if *listening_for_key {
+                let current_key = match *hotkey_vk.read() {
+                    0 => "Не задана".to_string(),
+                    vk => HotkeyManager::vk_to_key_name(vk),
+                };
+                let button_text = if self.listening_for_key {
                     "Нажмите любую клавишу..."
                 } else {
                     &current_key
                 };
 
-                let button_color = if *listening_for_key {
+                let button_color = if self.listening_for_key {
                     Color32::from_rgb(200, 150, 50)
                 } else {
                     Color32::from_rgb(70, 100, 180)
@@ -42,13 +63,13 @@ impl SettingsPanel {
                     .min_size(egui::vec2(150.0, 35.0)))
                     .clicked()
                 {
-                    *listening_for_key = true;
+                    self.listening_for_key = true;
                 }
 
                 let reset_button = ui.button("❌");
                 if reset_button.clicked() {
-                    *hotkey_vk.write() = HotkeyManager::vk_for_label("F6");
-                    *listening_for_key = false;
+                    *hotkey_vk.write() = 0;
+                    self.listening_for_key = false;
                 }
                 reset_button.on_hover_text("Сбросить");
             });
@@ -57,28 +78,89 @@ impl SettingsPanel {
             ui.separator();
             ui.add_space(10.0);
 
-            ui.label(RichText::new("Интервал между кликами (мс):").strong());
-            let mut cur = *interval_ms.read() as i32;
-            if ui.add(egui::Slider::new(&mut cur, 50..=2000)
-                .text_color(Color32::WHITE))
-                .changed()
+            let mut cps = *controls.cps.read();
+            ui.horizontal(|ui| {
+                ui.label(RichText::new("Скорость:").strong());
+                let interval_ms = *controls.interval_ms.read();
+                ui.selectable_value(&mut cps, None, "мс");
+                if ui.selectable_label(cps.is_some(), "CPS").clicked() && cps.is_none() {
+                    cps = Some((1000.0 / interval_ms.max(1) as f64).round().max(1.0));
+                }
+            });
+            match &mut cps {
+                None => {
+                    let mut cur = *controls.interval_ms.read();
+                    if ui.add(egui::Slider::new(&mut cur, timing::INTERVAL_RANGE)
+                        .logarithmic(true)
+                        .suffix(" ms")
+                        .text_color(Color32::WHITE))
+                        .changed()
+                    {
+                        *controls.interval_ms.write() = cur;
+                    }
+                }
+                Some(value) => {
+                    ui.add(egui::Slider::new(value, timing::CPS_RANGE)
+                        .logarithmic(true)
+                        .suffix(" кликов/с")
+                        .text_color(Color32::WHITE));
+                }
+            }
+            *controls.cps.write() = cps;
+
+            ui.add_space(5.0);
+            let base = timing::base_period(*controls.interval_ms.read(), cps);
+            Self::render_timing(ui, &mut controls.timing.write(), base.as_millis().max(1) as u64);
+
+            ui.add_space(10.0);
+            ui.separator();
+            ui.add_space(10.0);
+
+            ui.label(RichText::new("Тип клика:").strong());
             {
-                *interval_ms.write() = cur as u64;
+                let mut action = controls.click.write();
+                ui.horizontal(|ui| {
+                    for (button, label) in [
+                        (MouseButton::Left, "ЛКМ"),
+                        (MouseButton::Right, "ПКМ"),
+                        (MouseButton::Middle, "СКМ"),
+                        (MouseButton::X1, "X1"),
+                        (MouseButton::X2, "X2"),
+                    ] {
+                        ui.selectable_value(&mut action.button, button, label);
+                    }
+                });
+                ui.horizontal(|ui| {
+                    for (count, label) in [
+                        (ClickCount::Single, "Одинарный"),
+                        (ClickCount::Double, "Двойной"),
+                        (ClickCount::Triple, "Тройной"),
+                    ] {
+                        ui.selectable_value(&mut action.count, count, label);
+                    }
+                });
             }
 
             ui.add_space(10.0);
             ui.separator();
             ui.add_space(10.0);
 
-            let is_running = running.load(Ordering::SeqCst);
+            ui.label(RichText::new("Автостоп:").strong());
+            Self::render_stop_rules(ui, &mut controls.stop_rules.write());
+
+            ui.add_space(10.0);
+            ui.separator();
+            ui.add_space(10.0);
+
+            let is_running = controls.running.load(Ordering::SeqCst);
             ui.label(RichText::new("Статус:").strong());
             ui.horizontal(|ui| {
                 let pulse = (animation_progress * 2.0 * std::f32::consts::PI).sin().abs();
                 let pulse_color = if is_running {
                     Color32::from_rgb(
-                        (0 + (55.0 * pulse) as u8).min(255),
-                        (200 + (55.0 * pulse) as u8).min(255),
-                        (100 + (55.0 * pulse) as u8).min(255),
+                        (55.0 * pulse) as u8,
+                        200 + (55.0 * pulse) as u8,
+                        100 + (55.0 * pulse) as u8,
                     )
                 } else {
                     Color32::from_rgb(200, 80, 80)
@@ -97,10 +179,227 @@ impl SettingsPanel {
                     .strong());
             });
 
+            let rules = controls.stop_rules.read().tightest(*controls.run_limits.read());
+            if let Some(text) = Self::session_text(&controls.session.read(), &rules, is_running) {
+                ui.label(RichText::new(text)
+                    .color(Color32::from_rgb(180, 180, 200))
+                    .small());
+            }
+
+            let rate = *controls.rate.read();
+            if is_running && rate.measured_cps > 0.0 {
+                ui.label(RichText::new(format!(
+                    "Факт: {:.1} из {:.1} кликов/с, запаздывание {:.2} ms (макс. {:.2})",
+                    rate.measured_cps,
+                    rate.target_cps,
+                    rate.mean_lateness.as_secs_f64() * 1000.0,
+                    rate.max_lateness.as_secs_f64() * 1000.0,
+                ))
+                .color(Color32::from_rgb(180, 180, 200))
+                .small());
+            }
+
+            let stats = controls.stats.read().clone();
+            if stats.clicks > 0 {
+                ui.add_space(10.0);
+                ui.label(RichText::new("Статистика сессии:").strong());
+                Self::render_stats(ui, &stats);
+            }
+
             ui.add_space(5.0);
             ui.label(RichText::new("Клик идет в выбранное окно (оно не должно быть свернуто)")
                 .color(Color32::from_rgb(150, 150, 170))
                 .small());
+
+            if let Some(error) = backend_error {
+                ui.add_space(5.0);
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new(format!("⚠ Ввод недоступен: {}", error))
+                        .color(Color32::from_rgb(220, 160, 60))
+                        .small());
+                    restart = ui.small_button("🔄 Перезапустить").clicked();
+                });
+            }
+        });
+
+        restart
+    }
+
+    fn render_timing(ui: &mut egui::Ui, timing: &mut Timing, interval_ms: u64) {
+        let kinds = [
+            ("Фиксированный", IntervalDistribution::Fixed),
+            ("Равномерный", IntervalDistribution::Uniform { min_ms: interval_ms * 4 / 5, max_ms: interval_ms * 6 / 5 }),
+            ("Нормальный", IntervalDistribution::Normal { mean_ms: interval_ms as f64, std_dev_ms: interval_ms as f64 / 5.0 }),
+            ("Экспоненциальный", IntervalDistribution::Exponential { mean_ms: interval_ms as f64 }),
+            ("Гистограмма", IntervalDistribution::Histogram { buckets: Vec::new() }),
+        ];
+        let current = kinds.iter()
+            .position(|(_, kind)| std::mem::discriminant(kind) == std::mem::discriminant(&timing.distribution))
+            .unwrap_or(0);
+
+        ui.horizontal(|ui| {
+            ui.label("Разброс:");
+            egui::ComboBox::from_id_salt("interval_distribution")
+                .selected_text(kinds[current].0)
+                .show_ui(ui, |ui| {
+                    for (index, (label, kind)) in kinds.iter().enumerate() {
+                        if ui.selectable_label(index == current, *label).clicked() && index != current {
+                            timing.distribution = kind.clone();
+                        }
+                    }
+                });
+        });
+
+        ui.horizontal(|ui| match &mut timing.distribution {
+            IntervalDistribution::Fixed => {}
+            IntervalDistribution::Uniform { min_ms, max_ms } => {
+                ui.label("от");
+                ui.add(egui::DragValue::new(min_ms).range(1..=60_000).suffix(" ms"));
+                ui.label("до");
+                ui.add(egui::DragValue::new(max_ms).range(1..=60_000).suffix(" ms"));
+            }
+            IntervalDistribution::Normal { mean_ms, std_dev_ms } => {
+                ui.label("среднее");
+                ui.add(egui::DragValue::new(mean_ms).range(1.0..=60_000.0).suffix(" ms"));
+                ui.label("σ");
+                ui.add(egui::DragValue::new(std_dev_ms).range(0.0..=10_000.0).suffix(" ms"));
+            }
+            IntervalDistribution::Exponential { mean_ms } => {
+                ui.label("среднее");
+                ui.add(egui::DragValue::new(mean_ms).range(1.0..=60_000.0).suffix(" ms"));
+            }
+            IntervalDistribution::Histogram { buckets } => {
+                let text = if buckets.is_empty() {
+                    "Пусто — возьмите тайминги из записанного макроса".to_string()
+                } else {
+                    format!("{} интервалов в {} корзинах", buckets.iter().map(|(_, count)| count).sum::<u32>(), buckets.len())
+                };
+                ui.label(RichText::new(text).color(Color32::from_rgb(150, 150, 170)).small());
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let mut seeded = timing.seed.is_some();
+            if ui.checkbox(&mut seeded, "Фиксированный seed").changed() {
+                timing.seed = seeded.then_some(0);
+            }
+            if let Some(seed) = &mut timing.seed {
+                ui.add(egui::DragValue::new(seed));
+            }
+        });
+    }
+
+    fn render_stop_rules(ui: &mut egui::Ui, rules: &mut StopRules) {
+        ui.horizontal(|ui| {
+            let mut enabled = rules.max_clicks.is_some();
+            if ui.checkbox(&mut enabled, "После кликов:").changed() {
+                rules.max_clicks = enabled.then_some(100);
+            }
+            if let Some(max) = &mut rules.max_clicks {
+                ui.add(egui::DragValue::new(max).range(1..=u64::MAX));
+            }
+        });
+        ui.horizontal(|ui| {
+            let mut enabled = rules.max_duration_secs.is_some();
+            if ui.checkbox(&mut enabled, "Через:").changed() {
+                rules.max_duration_secs = enabled.then_some(60);
+            }
+            if let Some(secs) = &mut rules.max_duration_secs {
+                ui.add(egui::DragValue::new(secs).range(1..=u64::MAX).suffix(" с"));
+            }
+        });
+        ui.horizontal(|ui| {
+            let mut enabled = rules.stop_at.is_some();
+            if ui.checkbox(&mut enabled, "В:").changed() {
+                rules.stop_at = enabled.then(|| {
+                    let now = chrono::Local::now().time();
+                    chrono::NaiveTime::from_hms_opt(now.hour(), now.minute(), 0).unwrap_or_default() + chrono::TimeDelta::hours(1)
+                });
+            }
+            if let Some(time) = &mut rules.stop_at {
+                let (mut hour, mut minute) = (time.hour(), time.minute());
+                let changed = ui.add(egui::DragValue::new(&mut hour).range(0..=23)).changed()
+                    | ui.add(egui::DragValue::new(&mut minute).range(0..=59).custom_formatter(|m, _| format!("{:02}", m))).changed();
+                if changed {
+                    *time = chrono::NaiveTime::from_hms_opt(hour, minute, 0).unwrap_or(*time);
+                }
+            }
         });
     }
-}
\ No newline at end of file
+
+    fn render_stats(ui: &mut egui::Ui, stats: &SessionStats) {
+        let color = Color32::from_rgb(180, 180, 200);
+        ui.label(RichText::new(format!(
+            "Кликов: {}, не отправлено: {}, в среднем {:.1} кликов/с",
+            stats.clicks, stats.failed, stats.measured_cps,
+        ))
+        .color(if stats.failed > 0 { Color32::from_rgb(220, 160, 60) } else { color })
+        .small());
+        if let Some(error) = stats.interval_error {
+            ui.label(RichText::new(format!(
+                "Отклонение интервала: мин. {:+.2}, сред. {:+.2}, макс. {:+.2} ms",
+                error.min_ms, error.mean_ms, error.max_ms,
+            ))
+            .color(color)
+            .small());
+        }
+        cps_chart(ui, &stats.cps_history);
+    }
+
+    fn session_text(status: &SessionStatus, rules: &StopRules, is_running: bool) -> Option<String> {
+        if let (false, Some(reason)) = (is_running, status.stopped) {
+            let why = match reason {
+                StopReason::ClickLimit => "лимит кликов",
+                StopReason::TimeLimit => "лимит времени",
+                StopReason::StopTime => "время остановки",
+            };
+            return Some(format!("Остановлено: {} ({} кликов за {})", why, status.clicks, format_duration(status.elapsed)));
+        }
+        if !is_running {
+            return None;
+        }
+        let mut text = match rules.max_clicks {
+            Some(max) => format!("Кликов: {} / {}", status.clicks, max),
+            None => format!("Кликов: {}", status.clicks),
+        };
+        if let Some(remaining) = status.remaining {
+            text += &format!(", осталось {}", format_duration(remaining));
+        }
+        Some(text)
+    }
+}
+
+fn format_duration(duration: std::time::Duration) -> String {
+    let secs = duration.as_secs();
+    match secs / 3600 {
+        0 => format!("{}:{:02}", secs / 60, secs % 60),
+        hours => format!("{}:{:02}:{:02}", hours, secs / 60 % 60, secs % 60),
+    }
+}
+
+// Clicks per second over the session as a bar per second; the current,
+// still-counting second is left out.
+fn cps_chart(ui: &mut egui::Ui, history: &[(u64, u64)]) {
+    let complete = &history[..history.len().saturating_sub(1)];
+    if complete.is_empty() {
+        return;
+    }
+    let bars = complete
+        .iter()
+        .map(|(second, ticks)| Bar::new(*second as f64, *ticks as f64).width(0.9))
+        .collect();
+    let chart = BarChart::new("Клики в секунду", bars)
+        .color(Color32::from_rgb(0, 180, 100))
+        .element_formatter(Box::new(|bar, _| format!("{} с: {} кликов", bar.argument, bar.value)));
+    Plot::new("cps_chart")
+        .height(80.0)
+        .include_y(0.0)
+        .allow_zoom(false)
+        .allow_drag(false)
+        .allow_scroll(false)
+        .allow_boxed_zoom(false)
+        .allow_double_click_reset(false)
+        .show_x(false)
+        .show_y(false)
+        .show(ui, |plot| plot.bar_chart(chart));
+}
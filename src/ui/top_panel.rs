@@ -1,8 +1,9 @@
 use eframe::egui;
 use egui::{Color32, RichText};
-use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use std::sync::{Arc, atomic::Ordering};
 use parking_lot::RwLock;
 
+use crate::clicker::ClickerControls;
 use crate::hotkey_manager::HotkeyManager;
 
 pub struct TopPanel;
@@ -11,16 +12,17 @@ impl TopPanel {
     pub fn render(
         &self,
         ui: &mut egui::Ui,
-        running: &Arc<AtomicBool>,
+        controls: &ClickerControls,
         hotkey_vk: &Arc<RwLock<u32>>,
-        interval_ms: &Arc<RwLock<u64>>,
+        interval: &str,
         animation_progress: f32,
+        job_name: &str,
     ) {
         ui.horizontal_centered(|ui| {
             ui.heading(RichText::new("🚀 Be clicker").color(Color32::from_rgb(120, 180, 255)));
             ui.separator();
 
-            let is_running = running.load(Ordering::SeqCst);
+            let is_running = controls.running.load(Ordering::SeqCst);
             let status_color = if is_running {
                 Color32::from_rgb(0, 200, 100)
             } else {
@@ -44,12 +46,27 @@ impl TopPanel {
                 .strong());
 
             ui.separator();
-            ui.label(RichText::new(format!("Горячая клавиша: {}", HotkeyManager::vk_to_key_name(*hotkey_vk.read())))
+            ui.label(RichText::new(format!("Задание: {}", job_name))
                 .color(Color32::from_rgb(180, 180, 200)));
 
             ui.separator();
-            ui.label(RichText::new(format!("Интервал: {} ms", *interval_ms.read()))
+            let hotkey = match *hotkey_vk.read() {
+                0 => "—".to_string(),
+                vk => HotkeyManager::vk_to_key_name(vk),
+            };
+            ui.label(RichText::new(format!("Горячая клавиша: {}", hotkey))
                 .color(Color32::from_rgb(180, 180, 200)));
+
+            ui.separator();
+            ui.label(RichText::new(format!("Интервал: {}", interval))
+                .color(Color32::from_rgb(180, 180, 200)));
+
+            let stats = controls.stats.read();
+            if stats.clicks > 0 {
+                ui.separator();
+                ui.label(RichText::new(format!("Кликов: {} ({:.1}/с)", stats.clicks, stats.measured_cps))
+                    .color(Color32::from_rgb(180, 180, 200)));
+            }
         });
     }
 }
\ No newline at end of file
@@ -0,0 +1,189 @@
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::settings::WindowTarget;
+use crate::window_manager::WindowInfo;
+
+/// Desktop and taskbar windows that are listed like any other but are never
+/// worth clicking into, by class.
+const SHELL_CLASSES: &[&str] = &[
+    "Progman",
+    "WorkerW",
+    "Shell_TrayWnd",
+    "Shell_SecondaryTrayWnd",
+    "Windows.UI.Core.CoreWindow",
+    "plasmashell",
+    "Xfdesktop",
+    "Xfce4-panel",
+    "Gnome-shell",
+];
+const SHELL_TITLES: &[&str] = &["Program Manager"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum WindowSort {
+    /// As the window system lists them, roughly front to back.
+    #[default]
+    System,
+    Title,
+    Process,
+    /// Windows picked most recently first, then the rest in system order.
+    Recent,
+}
+
+/// How the window picker narrows and orders the list, kept between runs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowFilter {
+    /// Treat the search text as a case-insensitive regular expression.
+    pub regex: bool,
+    pub sort: WindowSort,
+    pub hide_shell: bool,
+    pub hide_tool: bool,
+    pub hide_empty: bool,
+}
+
+impl Default for WindowFilter {
+    fn default() -> Self {
+        Self {
+            regex: false,
+            sort: WindowSort::default(),
+            hide_shell: true,
+            hide_tool: true,
+            hide_empty: true,
+        }
+    }
+}
+
+/// The search text, prepared once: lowercased, or compiled when it is a
+/// regular expression. Keep it for as long as the text and the toggle stay
+/// the same.
+#[derive(Debug, Clone)]
+pub enum Query {
+    Any,
+    Text(String),
+    Pattern(Regex),
+}
+
+impl Query {
+    pub fn new(text: &str, regex: bool) -> Result<Self, String> {
+        let text = text.trim();
+        Ok(match (text.is_empty(), regex) {
+            (true, _) => Query::Any,
+            (false, false) => Query::Text(text.to_lowercase()),
+            (false, true) => {
+                Query::Pattern(RegexBuilder::new(text).case_insensitive(true).build().map_err(|e| e.to_string())?)
+            }
+        })
+    }
+
+    fn matches(&self, text: &str) -> bool {
+        match self {
+            Query::Any => true,
+            Query::Text(needle) => text.to_lowercase().contains(needle),
+            Query::Pattern(pattern) => pattern.is_match(text),
+        }
+    }
+}
+
+impl WindowFilter {
+    /// The windows matching `query` on title, class or process, in order.
+    /// `recent` holds the windows picked, most recently first.
+    pub fn apply<'a>(&self, query: &Query, windows: &'a [WindowInfo], recent: &[WindowTarget]) -> Vec<&'a WindowInfo> {
+        let matches = |text: &str| query.matches(text);
+        let mut list: Vec<&WindowInfo> = windows
+            .iter()
+            .filter(|window| !(self.hide_shell && is_shell(window)))
+            .filter(|window| !(self.hide_tool && window.tool))
+            .filter(|window| !(self.hide_empty && window.client_size.is_some_and(|(w, h)| w == 0 || h == 0)))
+            .filter(|window| {
+                matches(&window.title)
+                    || window.class.as_deref().is_some_and(matches)
+                    || window.process.as_deref().is_some_and(matches)
+            })
+            .collect();
+
+        // Stable sorts, so ties keep the system order.
+        match self.sort {
+            WindowSort::System => {}
+            WindowSort::Title => list.sort_by_cached_key(|window| window.title.to_lowercase()),
+            WindowSort::Process => list
+                .sort_by_cached_key(|window| (window.process.is_none(), window.process.as_deref().map(str::to_lowercase))),
+            WindowSort::Recent => {
+                list.sort_by_key(|window| recent.iter().position(|target| target.matches(window)).unwrap_or(usize::MAX))
+            }
+        }
+        list
+    }
+}
+
+fn is_shell(window: &WindowInfo) -> bool {
+    SHELL_TITLES.contains(&window.title.as_str())
+        || window.class.as_deref().is_some_and(|class| SHELL_CLASSES.contains(&class))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(hwnd: isize, title: &str, class: &str, process: &str) -> WindowInfo {
+        WindowInfo {
+            hwnd,
+            title: title.to_string(),
+            class: Some(class.to_string()),
+            process: Some(process.to_string()),
+            client_size: Some((800, 600)),
+            visible: true,
+            ..WindowInfo::default()
+        }
+    }
+
+    fn windows() -> Vec<WindowInfo> {
+        vec![
+            window(1, "Notes - Editor", "Edit", "notepad.exe"),
+            window(2, "Program Manager", "Progman", "explorer.exe"),
+            WindowInfo { tool: true, ..window(3, "Palette", "Tool", "paint.exe") },
+            WindowInfo { client_size: Some((0, 0)), ..window(4, "Hidden helper", "Helper", "helper.exe") },
+            window(5, "Game", "UnityWndClass", "game.exe"),
+            window(6, "another game", "UnityWndClass", "Game.exe"),
+        ]
+    }
+
+    fn handles(list: Vec<&WindowInfo>) -> Vec<isize> {
+        list.into_iter().map(|window| window.hwnd).collect()
+    }
+
+    #[test]
+    fn hides_shell_tool_and_empty_windows() {
+        let windows = windows();
+        assert_eq!(handles(WindowFilter::default().apply(&Query::Any, &windows, &[])), vec![1, 5, 6]);
+        let everything = WindowFilter { hide_shell: false, hide_tool: false, hide_empty: false, ..WindowFilter::default() };
+        assert_eq!(everything.apply(&Query::Any, &windows, &[]).len(), windows.len());
+    }
+
+    #[test]
+    fn searches_title_class_and_process() {
+        let windows = windows();
+        let search = |text: &str, regex: bool| {
+            Query::new(text, regex).map(|query| handles(WindowFilter::default().apply(&query, &windows, &[])))
+        };
+        assert_eq!(search("GAME", false), Ok(vec![5, 6]));
+        assert_eq!(search("unitywnd", false), Ok(vec![5, 6]));
+        assert_eq!(search("notepad", false), Ok(vec![1]));
+        assert_eq!(search("^game$", true), Ok(vec![5]));
+        assert!(search("(", true).is_err());
+    }
+
+    #[test]
+    fn sorts_by_title_process_and_recent_use() {
+        let windows = windows();
+        let sorted = |sort, recent: &[WindowTarget]| {
+            handles(WindowFilter { sort, ..WindowFilter::default() }.apply(&Query::Any, &windows, recent))
+        };
+        assert_eq!(sorted(WindowSort::Title, &[]), vec![6, 5, 1]);
+        assert_eq!(sorted(WindowSort::Process, &[]), vec![5, 6, 1]);
+        // By title, class and process, so the order survives a restart
+        // that gives every window a new handle.
+        let recent = [WindowTarget::from_window(&windows[5]), WindowTarget::from_window(&windows[0])];
+        assert_eq!(sorted(WindowSort::Recent, &recent), vec![6, 1, 5]);
+    }
+}
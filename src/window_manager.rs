@@ -1,30 +1,584 @@
-use windows::core::BOOL;
-use windows::Win32::Foundation::{HWND, LPARAM};
-use windows::Win32::UI::WindowsAndMessaging::{EnumWindows, GetWindowTextW, IsWindowVisible};
+use std::path::PathBuf;
+#[cfg(windows)]
+use windows::core::{BOOL, PWSTR};
+#[cfg(windows)]
+use windows::Win32::Foundation::{CloseHandle, HWND, LPARAM, POINT, RECT};
+#[cfg(windows)]
+use windows::Win32::Graphics::Gdi::{GetDC, GetPixel, ReleaseDC, ScreenToClient, CLR_INVALID};
+#[cfg(windows)]
+use windows::Win32::System::Threading::{
+    OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+#[cfg(windows)]
+use windows::Win32::UI::WindowsAndMessaging::{
+    EnumWindows, GetClassNameW, GetClientRect, GetWindow, GetWindowLongW, GetWindowTextW, GetWindowThreadProcessId,
+    IsIconic, IsWindowVisible, GWL_EXSTYLE, GW_OWNER, WS_EX_TOOLWINDOW,
+};
+#[cfg(target_os = "linux")]
+use std::cell::RefCell;
+#[cfg(target_os = "linux")]
+use x11rb::{
+    connection::Connection,
+    errors::{ConnectionError, ReplyError},
+    protocol::xproto::{Atom, AtomEnum, ConnectionExt as _, GetPropertyReply, ImageFormat, MapState, Window},
+    rust_connection::RustConnection,
+};
+
+#[cfg(any(windows, target_os = "linux"))]
+use tracing::debug;
+#[cfg(target_os = "linux")]
+use tracing::warn;
+
+/// A top-level window as offered for targeting. Apart from the handle and
+/// title everything is best effort and stays empty when the window system or
+/// the owning process does not say.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WindowInfo {
+    pub hwnd: isize,
+    pub title: String,
+    /// `GetClassName` on Windows, the class half of `WM_CLASS` on X11.
+    pub class: Option<String>,
+    pub pid: Option<u32>,
+    /// Executable file name, e.g. `chrome.exe`.
+    pub process: Option<String>,
+    pub exe_path: Option<PathBuf>,
+    pub client_size: Option<(i32, i32)>,
+    pub minimized: bool,
+    pub visible: bool,
+    /// Tool palette, panel or desktop rather than an application window
+    /// (`WS_EX_TOOLWINDOW`, or an EWMH window type to that effect).
+    pub tool: bool,
+    /// Window this one belongs to, e.g. the main window of a dialog.
+    pub owner: Option<isize>,
+}
+
+impl WindowInfo {
+    /// Title plus the process, to tell apart windows with the same title.
+    pub fn label(&self) -> String {
+        match &self.process {
+            Some(process) => format!("{} — {}", self.title, process),
+            None => self.title.clone(),
+        }
+    }
+}
 
 pub struct WindowManager;
 
+#[cfg(windows)]
 impl WindowManager {
     unsafe extern "system" fn enum_proc(hwnd: HWND, lparam: LPARAM) -> BOOL {
         unsafe {
             if IsWindowVisible(hwnd).as_bool() {
-                let mut buf = [0u16; 512];
-                let len = GetWindowTextW(hwnd, &mut buf);
-                if len > 0 {
-                    let title = String::from_utf16_lossy(&buf[..len as usize]);
-                    let vec = &mut *(lparam.0 as *mut Vec<(String, isize)>);
-                    vec.push((title, hwnd.0 as isize));
-                }
+                let vec = &mut *(lparam.0 as *mut Vec<isize>);
+                vec.push(hwnd.0 as isize);
             }
         }
         BOOL(1)
     }
 
-    pub fn get_windows_list() -> Vec<(String, isize)> {
-        let mut list: Vec<(String, isize)> = Vec::new();
+    /// Visible top-level windows that have a title.
+    pub fn get_windows_list() -> Vec<WindowInfo> {
+        let mut handles: Vec<isize> = Vec::new();
         unsafe {
-            EnumWindows(Some(Self::enum_proc), LPARAM(&mut list as *mut _ as isize)).ok();
+            EnumWindows(Some(Self::enum_proc), LPARAM(&mut handles as *mut _ as isize)).ok();
         }
+        let list: Vec<WindowInfo> = handles.into_iter().filter_map(Self::window_info).collect();
+        debug!(count = list.len(), "window list refreshed");
         list
     }
-}
\ No newline at end of file
+
+    fn window_info(hwnd: isize) -> Option<WindowInfo> {
+        let title = Self::window_title(hwnd)?;
+        let handle = HWND(hwnd as *mut _);
+        let mut class = [0u16; 256];
+        let class_len = unsafe { GetClassNameW(handle, &mut class) };
+        let mut pid = 0u32;
+        unsafe { GetWindowThreadProcessId(handle, Some(&mut pid)) };
+        let exe_path = if pid != 0 { Self::process_path(pid) } else { None };
+        let owner = unsafe { GetWindow(handle, GW_OWNER) }
+            .ok()
+            .filter(|owner| !owner.is_invalid())
+            .map(|owner| owner.0 as isize);
+
+        Some(WindowInfo {
+            hwnd,
+            title,
+            class: (class_len > 0).then(|| String::from_utf16_lossy(&class[..class_len as usize])),
+            pid: (pid != 0).then_some(pid),
+            process: exe_path
+                .as_ref()
+                .and_then(|path| path.file_name())
+                .map(|name| name.to_string_lossy().into_owned()),
+            exe_path,
+            client_size: Self::client_size(hwnd),
+            minimized: unsafe { IsIconic(handle) }.as_bool(),
+            visible: unsafe { IsWindowVisible(handle) }.as_bool(),
+            tool: unsafe { GetWindowLongW(handle, GWL_EXSTYLE) } as u32 & WS_EX_TOOLWINDOW.0 != 0,
+            owner,
+        })
+    }
+
+    // Limited query rights are enough for the image name and are granted for
+    // most processes of the same user, elevated ones excepted.
+    fn process_path(pid: u32) -> Option<PathBuf> {
+        unsafe {
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+            let mut buf = [0u16; 1024];
+            let mut len = buf.len() as u32;
+            let result = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buf.as_mut_ptr()), &mut len);
+            CloseHandle(process).ok();
+            result.ok()?;
+            Some(PathBuf::from(String::from_utf16_lossy(&buf[..len as usize])))
+        }
+    }
+
+    pub fn window_title(hwnd: isize) -> Option<String> {
+        let mut buf = [0u16; 512];
+        let len = unsafe { GetWindowTextW(HWND(hwnd as *mut _), &mut buf) };
+        (len > 0).then(|| String::from_utf16_lossy(&buf[..len as usize]))
+    }
+
+    /// Colour of a client-area pixel as 0xRRGGBB.
+    pub fn pixel_at(hwnd: isize, x: i32, y: i32) -> Option<u32> {
+        let hwnd = HWND(hwnd as *mut _);
+        unsafe {
+            let hdc = GetDC(Some(hwnd));
+            if hdc.is_invalid() {
+                return None;
+            }
+            let color = GetPixel(hdc, x, y);
+            ReleaseDC(Some(hwnd), hdc);
+            // COLORREF is 0x00BBGGRR.
+            (color.0 != CLR_INVALID).then(|| {
+                let bgr = color.0;
+                ((bgr & 0xFF) << 16) | (bgr & 0xFF00) | ((bgr >> 16) & 0xFF)
+            })
+        }
+    }
+
+    pub fn client_size(hwnd: isize) -> Option<(i32, i32)> {
+        let mut rect = RECT::default();
+        unsafe { GetClientRect(HWND(hwnd as *mut _), &mut rect) }.ok()?;
+        Some((rect.right - rect.left, rect.bottom - rect.top))
+    }
+
+    pub fn screen_to_client(hwnd: isize, x: i32, y: i32) -> Option<(i32, i32)> {
+        let mut point = POINT { x, y };
+        unsafe { ScreenToClient(HWND(hwnd as *mut _), &mut point) }
+            .as_bool()
+            .then_some((point.x, point.y))
+    }
+}
+
+#[cfg(target_os = "linux")]
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        _NET_CLIENT_LIST,
+        _NET_WM_NAME,
+        _NET_WM_PID,
+        _NET_WM_STATE,
+        _NET_WM_STATE_HIDDEN,
+        _NET_WM_WINDOW_TYPE,
+        _NET_WM_WINDOW_TYPE_DESKTOP,
+        _NET_WM_WINDOW_TYPE_DOCK,
+        _NET_WM_WINDOW_TYPE_TOOLBAR,
+        _NET_WM_WINDOW_TYPE_UTILITY,
+        UTF8_STRING,
+    }
+}
+
+// An open display with its atoms, kept per thread so the script host can
+// ask for titles and pixels every tick without reconnecting each time.
+#[cfg(target_os = "linux")]
+struct Display {
+    name: Option<String>,
+    conn: RustConnection,
+    screen: usize,
+    atoms: Atoms,
+}
+
+#[cfg(target_os = "linux")]
+thread_local! {
+    static DISPLAY: RefCell<Option<Display>> = const { RefCell::new(None) };
+}
+
+#[cfg(target_os = "linux")]
+impl WindowManager {
+    pub fn get_windows_list() -> Vec<WindowInfo> {
+        match Self::list_on_display(None) {
+            Ok(list) => {
+                debug!(count = list.len(), "window list refreshed");
+                list
+            }
+            Err(error) => {
+                warn!(%error, "could not list windows");
+                Vec::new()
+            }
+        }
+    }
+
+    // Reads the window manager's client list (EWMH), so only real top-level
+    // application windows show up, in the order the WM reports them.
+    fn list_on_display(display: Option<&str>) -> Result<Vec<WindowInfo>, Box<dyn std::error::Error>> {
+        let (conn, screen) = x11rb::connect(display)?;
+        let root = conn.setup().roots[screen].root;
+        let atoms = Atoms::new(&conn)?.reply()?;
+        let clients = conn
+            .get_property(false, root, atoms._NET_CLIENT_LIST, AtomEnum::WINDOW, 0, u32::MAX)?
+            .reply()?;
+        let own_pid = std::process::id();
+
+        let mut list = Vec::new();
+        for window in clients.value32().into_iter().flatten() {
+            let pid = Self::property(&conn, window, atoms._NET_WM_PID, AtomEnum::CARDINAL.into())
+                .and_then(|p| p.value32().and_then(|mut v| v.next()));
+            if pid == Some(own_pid) {
+                continue;
+            }
+
+            if let Some(title) = Self::title(&conn, &atoms, window) {
+                list.push(Self::window_info(&conn, &atoms, window, title, pid));
+            }
+        }
+        Ok(list)
+    }
+
+    fn window_info(conn: &RustConnection, atoms: &Atoms, window: Window, title: String, pid: Option<u32>) -> WindowInfo {
+        // Process details come from /proc, so they are only right for
+        // clients on this machine; a remote client's PID means nothing here.
+        let process = pid
+            .and_then(|pid| std::fs::read_to_string(format!("/proc/{}/comm", pid)).ok())
+            .map(|comm| comm.trim_end().to_string());
+        let exe_path = pid.and_then(|pid| std::fs::read_link(format!("/proc/{}/exe", pid)).ok());
+        let minimized = Self::property(conn, window, atoms._NET_WM_STATE, AtomEnum::ATOM.into())
+            .and_then(|reply| reply.value32().map(|mut states| states.any(|state| state == atoms._NET_WM_STATE_HIDDEN)))
+            .unwrap_or(false);
+        let tool_types = [
+            atoms._NET_WM_WINDOW_TYPE_DESKTOP,
+            atoms._NET_WM_WINDOW_TYPE_DOCK,
+            atoms._NET_WM_WINDOW_TYPE_TOOLBAR,
+            atoms._NET_WM_WINDOW_TYPE_UTILITY,
+        ];
+        let tool = Self::property(conn, window, atoms._NET_WM_WINDOW_TYPE, AtomEnum::ATOM.into())
+            .and_then(|reply| reply.value32().map(|mut types| types.any(|kind| tool_types.contains(&kind))))
+            .unwrap_or(false);
+        let visible = conn
+            .get_window_attributes(window)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .is_some_and(|attributes| attributes.map_state == MapState::VIEWABLE);
+        let owner = Self::property(conn, window, AtomEnum::WM_TRANSIENT_FOR.into(), AtomEnum::WINDOW.into())
+            .and_then(|reply| reply.value32().and_then(|mut v| v.next()))
+            .filter(|owner| *owner != x11rb::NONE)
+            .map(|owner| owner as isize);
+        let client_size = conn
+            .get_geometry(window)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .map(|geometry| (geometry.width as i32, geometry.height as i32));
+
+        WindowInfo {
+            hwnd: window as isize,
+            title,
+            class: Self::wm_class(conn, window),
+            pid,
+            process,
+            exe_path,
+            client_size,
+            minimized,
+            visible,
+            tool,
+            owner,
+        }
+    }
+
+    fn title(conn: &RustConnection, atoms: &Atoms, window: Window) -> Option<String> {
+        Self::text_property(conn, window, atoms._NET_WM_NAME, atoms.UTF8_STRING)
+            .filter(|t| !t.is_empty())
+            .or_else(|| Self::text_property(conn, window, AtomEnum::WM_NAME.into(), AtomEnum::ANY.into()))
+            .filter(|t| !t.is_empty())
+            .or_else(|| Self::wm_class(conn, window).map(|class| format!("[{}]", class)))
+    }
+
+    // Runs `f` on this thread's connection to `display`, opening it first
+    // if needed. A connection that broke is dropped, so the next call
+    // reconnects, e.g. after the X server restarted.
+    fn with_display<T>(
+        display: Option<&str>,
+        f: impl FnOnce(&Display) -> Result<T, Box<dyn std::error::Error>>,
+    ) -> Result<T, Box<dyn std::error::Error>> {
+        DISPLAY.with(|cached| {
+            let mut cached = cached.borrow_mut();
+            if cached.as_ref().is_none_or(|open| open.name.as_deref() != display) {
+                let (conn, screen) = x11rb::connect(display)?;
+                let atoms = Atoms::new(&conn)?.reply()?;
+                *cached = Some(Display { name: display.map(str::to_owned), conn, screen, atoms });
+            }
+            let result = f(cached.as_ref().unwrap());
+            if let Err(error) = &result
+                && (error.is::<ConnectionError>()
+                    || matches!(error.downcast_ref::<ReplyError>(), Some(ReplyError::ConnectionError(_))))
+            {
+                *cached = None;
+            }
+            result
+        })
+    }
+
+    pub fn window_title(window: isize) -> Option<String> {
+        Self::with_display(None, |display| Ok(Self::title(&display.conn, &display.atoms, window as Window)))
+            .ok()
+            .flatten()
+    }
+
+    /// Colour of a window pixel as 0xRRGGBB. Only works while the window is
+    /// mapped and that part of it is visible.
+    pub fn pixel_at(window: isize, x: i32, y: i32) -> Option<u32> {
+        Self::pixel_at_on_display(None, window, x, y).ok()
+    }
+
+    fn pixel_at_on_display(
+        display: Option<&str>,
+        window: isize,
+        x: i32,
+        y: i32,
+    ) -> Result<u32, Box<dyn std::error::Error>> {
+        let (x, y) = (i16::try_from(x)?, i16::try_from(y)?);
+        Self::with_display(display, |Display { conn, .. }| {
+            let image = conn.get_image(ImageFormat::Z_PIXMAP, window as Window, x, y, 1, 1, !0)?.reply()?;
+            // 24/32-bit TrueColor visuals, which is all any modern server uses.
+            let bytes: [u8; 4] = image.data.get(..4).ok_or("unsupported pixel format")?.try_into()?;
+            let pixel = match conn.setup().image_byte_order {
+                x11rb::protocol::xproto::ImageOrder::MSB_FIRST => u32::from_be_bytes(bytes),
+                _ => u32::from_le_bytes(bytes),
+            };
+            Ok(pixel & 0xFF_FFFF)
+        })
+    }
+
+    pub fn client_size(window: isize) -> Option<(i32, i32)> {
+        Self::client_size_on_display(None, window).ok()
+    }
+
+    fn client_size_on_display(display: Option<&str>, window: isize) -> Result<(i32, i32), Box<dyn std::error::Error>> {
+        Self::with_display(display, |Display { conn, .. }| {
+            let geometry = conn.get_geometry(window as Window)?.reply()?;
+            Ok((geometry.width as i32, geometry.height as i32))
+        })
+    }
+
+    /// Converts root-window (screen) coordinates into the window's own.
+    pub fn screen_to_client(window: isize, x: i32, y: i32) -> Option<(i32, i32)> {
+        Self::screen_to_client_on_display(None, window, x, y).ok()
+    }
+
+    fn screen_to_client_on_display(
+        display: Option<&str>,
+        window: isize,
+        x: i32,
+        y: i32,
+    ) -> Result<(i32, i32), Box<dyn std::error::Error>> {
+        let clamp = |v: i32| v.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+        Self::with_display(display, |Display { conn, screen, .. }| {
+            let root = conn.setup().roots[*screen].root;
+            let reply = conn.translate_coordinates(root, window as Window, clamp(x), clamp(y))?.reply()?;
+            Ok((reply.dst_x as i32, reply.dst_y as i32))
+        })
+    }
+
+    fn property(conn: &RustConnection, window: Window, property: Atom, type_: Atom) -> Option<GetPropertyReply> {
+        conn.get_property(false, window, property, type_, 0, u32::MAX)
+            .ok()?
+            .reply()
+            .ok()
+            .filter(|reply| reply.type_ != x11rb::NONE)
+    }
+
+    fn text_property(conn: &RustConnection, window: Window, property: Atom, type_: Atom) -> Option<String> {
+        let reply = Self::property(conn, window, property, type_)?;
+        Some(String::from_utf8_lossy(&reply.value).trim_end_matches('\0').to_string())
+    }
+
+    // WM_CLASS is "instance\0class\0"; the class part is the readable one.
+    fn wm_class(conn: &RustConnection, window: Window) -> Option<String> {
+        let raw = Self::text_property(conn, window, AtomEnum::WM_CLASS.into(), AtomEnum::STRING.into())?;
+        raw.split('\0').rfind(|s| !s.is_empty()).map(str::to_owned)
+    }
+}
+
+#[cfg(all(not(windows), not(target_os = "linux")))]
+impl WindowManager {
+    pub fn get_windows_list() -> Vec<WindowInfo> {
+        Vec::new()
+    }
+
+    pub fn window_title(_hwnd: isize) -> Option<String> {
+        None
+    }
+
+    pub fn pixel_at(_hwnd: isize, _x: i32, _y: i32) -> Option<u32> {
+        None
+    }
+
+    pub fn client_size(_hwnd: isize) -> Option<(i32, i32)> {
+        None
+    }
+
+    pub fn screen_to_client(_hwnd: isize, _x: i32, _y: i32) -> Option<(i32, i32)> {
+        None
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::backend::x11::tests::Xvfb;
+    use x11rb::protocol::xproto::{CreateWindowAux, PropMode, WindowClass};
+    use x11rb::wrapper::ConnectionExt as _;
+    use x11rb::COPY_DEPTH_FROM_PARENT;
+
+    fn dummy_window(conn: &RustConnection, root: Window) -> Window {
+        let window = conn.generate_id().unwrap();
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            0,
+            0,
+            100,
+            100,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            0,
+            &CreateWindowAux::new(),
+        )
+        .unwrap();
+        window
+    }
+
+    #[test]
+    fn lists_ewmh_client_windows() {
+        let Some(xvfb) = Xvfb::start() else {
+            eprintln!("Xvfb not found, skipping");
+            return;
+        };
+        let (conn, screen) = x11rb::connect(Some(&xvfb.display)).unwrap();
+        let root = conn.setup().roots[screen].root;
+        let atoms = Atoms::new(&conn).unwrap().reply().unwrap();
+
+        let utf8 = dummy_window(&conn, root);
+        conn.change_property8(PropMode::REPLACE, utf8, atoms._NET_WM_NAME, atoms.UTF8_STRING, "Тест App".as_bytes())
+            .unwrap();
+
+        let legacy = dummy_window(&conn, root);
+        conn.change_property8(PropMode::REPLACE, legacy, AtomEnum::WM_NAME, AtomEnum::STRING, b"xterm")
+            .unwrap();
+
+        let class_only = dummy_window(&conn, root);
+        conn.change_property8(PropMode::REPLACE, class_only, AtomEnum::WM_CLASS, AtomEnum::STRING, b"navigator\0Firefox\0")
+            .unwrap();
+
+        let ours = dummy_window(&conn, root);
+        conn.change_property8(PropMode::REPLACE, ours, atoms._NET_WM_NAME, atoms.UTF8_STRING, b"Be Clicker")
+            .unwrap();
+        conn.change_property32(PropMode::REPLACE, ours, atoms._NET_WM_PID, AtomEnum::CARDINAL, &[std::process::id()])
+            .unwrap();
+
+        let untitled = dummy_window(&conn, root);
+
+        conn.change_property32(
+            PropMode::REPLACE,
+            root,
+            atoms._NET_CLIENT_LIST,
+            AtomEnum::WINDOW,
+            &[utf8, legacy, class_only, ours, untitled],
+        )
+        .unwrap();
+        conn.sync().unwrap();
+
+        let list = WindowManager::list_on_display(Some(&xvfb.display)).unwrap();
+        let titles: Vec<_> = list.iter().map(|info| (info.title.as_str(), info.hwnd)).collect();
+        assert_eq!(
+            titles,
+            vec![
+                ("Тест App", utf8 as isize),
+                ("xterm", legacy as isize),
+                ("[Firefox]", class_only as isize),
+            ]
+        );
+        assert_eq!(list[2].class.as_deref(), Some("Firefox"));
+        assert_eq!(list[0].client_size, Some((100, 100)));
+        assert!(!list[0].visible && !list[0].minimized);
+    }
+
+    #[test]
+    fn client_geometry_and_screen_coordinates() {
+        let Some(xvfb) = Xvfb::start() else {
+            eprintln!("Xvfb not found, skipping");
+            return;
+        };
+        let (conn, screen) = x11rb::connect(Some(&xvfb.display)).unwrap();
+        let root = conn.setup().roots[screen].root;
+        let window = conn.generate_id().unwrap();
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            40,
+            30,
+            200,
+            120,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            0,
+            &CreateWindowAux::new(),
+        )
+        .unwrap();
+        conn.sync().unwrap();
+
+        let display = Some(xvfb.display.as_str());
+        assert_eq!(WindowManager::client_size_on_display(display, window as isize).unwrap(), (200, 120));
+        assert_eq!(
+            WindowManager::screen_to_client_on_display(display, window as isize, 140, 90).unwrap(),
+            (100, 60)
+        );
+    }
+
+    #[test]
+    fn reads_pixel_colour_of_mapped_window() {
+        let Some(xvfb) = Xvfb::start() else {
+            eprintln!("Xvfb not found, skipping");
+            return;
+        };
+        let (conn, screen) = x11rb::connect(Some(&xvfb.display)).unwrap();
+        let root = conn.setup().roots[screen].root;
+        let window = conn.generate_id().unwrap();
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            0,
+            0,
+            50,
+            50,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            0,
+            &CreateWindowAux::new().background_pixel(0x00FF8020),
+        )
+        .unwrap();
+        conn.map_window(window).unwrap();
+        conn.clear_area(false, window, 0, 0, 0, 0).unwrap();
+        conn.sync().unwrap();
+
+        let display = Some(xvfb.display.as_str());
+        assert_eq!(WindowManager::pixel_at_on_display(display, window as isize, 10, 10).unwrap(), 0xFF8020);
+
+        // The second read goes over the same connection.
+        let client_id = || DISPLAY.with(|cached| cached.borrow().as_ref().map(|open| open.conn.setup().resource_id_base));
+        let first = client_id();
+        assert_eq!(WindowManager::pixel_at_on_display(display, window as isize, 10, 10).unwrap(), 0xFF8020);
+        assert!(first.is_some());
+        assert_eq!(client_id(), first);
+    }
+}
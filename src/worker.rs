@@ -0,0 +1,39 @@
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use std::thread::JoinHandle;
+
+/// An owned background thread. The body gets a shutdown flag to poll;
+/// `stop` (or dropping the worker) raises it and joins the thread, so
+/// whatever the body does on its way out has happened when `stop` returns.
+#[must_use = "dropping a Worker stops its thread"]
+pub struct Worker {
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Worker {
+    pub fn spawn(name: &str, body: impl FnOnce(&AtomicBool) + Send + 'static) -> Self {
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let flag = shutdown.clone();
+        let thread = std::thread::Builder::new()
+            .name(name.to_string())
+            .spawn(move || body(&flag))
+            .expect("failed to spawn worker thread");
+        Self {
+            shutdown,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}